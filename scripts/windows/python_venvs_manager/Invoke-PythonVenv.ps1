@@ -10,60 +10,62 @@
 # Capture the script directory at load time (not at function invocation time)
 $script:_PythonVenvToolsDir = $PSScriptRoot
 
-function ape {
-    $marker = Join-Path $env:TEMP "_venv_activate_path.txt"
-    Remove-Item $marker -ErrorAction SilentlyContinue
+function Invoke-PythonVenvCore {
+    param([string]$CoreName, [object[]]$CoreArgs)
 
     $exeDir = $script:_PythonVenvToolsDir
     if (-not $exeDir) {
-        $cmd = Get-Command ape-core.exe -ErrorAction SilentlyContinue
+        $cmd = Get-Command "$CoreName.exe" -ErrorAction SilentlyContinue
         if ($cmd) { $exeDir = Split-Path $cmd.Source }
     }
 
     if (-not $exeDir) {
-        Write-Error "Cannot find ape-core.exe. Is it installed?"
+        Write-Error "Cannot find $CoreName.exe. Is it installed?"
         return
     }
 
-    & "$exeDir\ape-core.exe" @args
+    # Private handoff file for this invocation only; the core refuses to
+    # write anywhere else, so concurrent terminals cannot cross over
+    $handoff = New-TemporaryFile
+    $previous = $env:APE_HANDOFF
+    try {
+        $env:APE_HANDOFF = $handoff.FullName
+        & "$exeDir\$CoreName.exe" @CoreArgs
+        $exitCode = $LASTEXITCODE
+        # Line 1 is the environment, line 2 the conda installation activating it
+        $handoffLines = @(Get-Content $handoff.FullName)
+        $venvPath = $handoffLines | Select-Object -First 1
+        $condaRoot = $handoffLines | Select-Object -Skip 1 -First 1
+    } finally {
+        $env:APE_HANDOFF = $previous
+        Remove-Item $handoff.FullName -ErrorAction SilentlyContinue
+    }
 
-    if (Test-Path $marker) {
-        $venvPath = (Get-Content $marker -Raw).Trim()
-        Remove-Item $marker -ErrorAction SilentlyContinue
-        $activatePs1 = Join-Path $venvPath "Scripts\Activate.ps1"
+    if ($venvPath -and $condaRoot) {
+        $condaHook = Join-Path $condaRoot.Trim() "shell\condabin\conda-hook.ps1"
+        if (Test-Path $condaHook) {
+            . $condaHook
+            conda activate $venvPath.Trim()
+        } else {
+            Write-Warning "conda-hook.ps1 not found at: $condaHook"
+        }
+    } elseif ($venvPath) {
+        $activatePs1 = Join-Path $venvPath.Trim() "Scripts\Activate.ps1"
         if (Test-Path $activatePs1) {
             . $activatePs1
         } else {
             Write-Warning "Activate.ps1 not found at: $activatePs1"
         }
     }
-}
-
-function spe {
-    $marker = Join-Path $env:TEMP "_venv_activate_path.txt"
-    Remove-Item $marker -ErrorAction SilentlyContinue
 
-    $exeDir = $script:_PythonVenvToolsDir
-    if (-not $exeDir) {
-        $cmd = Get-Command spe-core.exe -ErrorAction SilentlyContinue
-        if ($cmd) { $exeDir = Split-Path $cmd.Source }
-    }
-
-    if (-not $exeDir) {
-        Write-Error "Cannot find spe-core.exe. Is it installed?"
-        return
-    }
+    # Activate.ps1 resets it, so restore the core's exit code for callers
+    $global:LASTEXITCODE = $exitCode
+}
 
-    & "$exeDir\spe-core.exe" @args
+function ape {
+    Invoke-PythonVenvCore -CoreName "ape-core" -CoreArgs $args
+}
 
-    if (Test-Path $marker) {
-        $venvPath = (Get-Content $marker -Raw).Trim()
-        Remove-Item $marker -ErrorAction SilentlyContinue
-        $activatePs1 = Join-Path $venvPath "Scripts\Activate.ps1"
-        if (Test-Path $activatePs1) {
-            . $activatePs1
-        } else {
-            Write-Warning "Activate.ps1 not found at: $activatePs1"
-        }
-    }
+function spe {
+    Invoke-PythonVenvCore -CoreName "spe-core" -CoreArgs $args
 }
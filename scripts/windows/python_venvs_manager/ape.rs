@@ -1,764 +1,228 @@
-use colored::*;
-use rayon::prelude::*;
-use serde::{Deserialize, Serialize};
-use std::env;
-use std::fs::{self};
-use std::io::{self};
-use std::path::{Path, PathBuf};
-use walkdir::WalkDir;
-
-const CACHE_FILENAME: &str = "python_venv_cache.json";
-const CONFIG_FILENAME: &str = "python_venv_config.toml";
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct Environment {
-    name: String,
-    env_type: String,
-    path: PathBuf,
-}
-
-#[derive(Debug, Deserialize)]
-struct UserConfig {
-    directories: Option<Vec<String>>,
-}
+//! `ape`: activate an environment by name
 
-struct Config {
-    help: bool,
-    verbose: bool,
-    scan: bool,
-    clean: bool,
-    no_color: bool,
-    env_name: Option<String>,
-    unknown_flag: Option<String>,
-}
-
-fn main() {
-    let config = parse_args();
-
-    // Check for unknown flags
-    if let Some(flag) = &config.unknown_flag {
-        eprintln!();
-        print_warning(&format!("Unknown flag \"{}\"", flag), config.no_color);
-        eprintln!("Run 'ape --help' for usage information.");
-        eprintln!();
-        return;
-    }
+use crate::cli::{
+    self, CLEAN, FORCE, FULL, Globals, HELP, INIT, NO_COLOR, Opt, SCAN, SHELL, VERBOSE,
+};
+use std::env;
+use std::path::Path;
+use venv_core::activation::activate_environment;
+use venv_core::cache::{get_cache_path, migrate_legacy_cache, record_use, rescan};
+use venv_core::config::{SearchDir, get_search_dirs, print_search_dirs};
+use venv_core::discovery::find_environment;
+use venv_core::health::ensure_healthy;
+use venv_core::output::print_debug;
+use venv_core::project::find_project_environment;
+use venv_core::{Environment, Error, Result, outln};
+
+/// Options `ape` accepts, in the order its help lists them
+const OPTIONS: &[&Opt] = &[
+    &HELP, &VERBOSE, &SCAN, &FULL, &FORCE, &CLEAN, &NO_COLOR, &SHELL, &INIT,
+];
+
+/// Entry point when invoked as `ape`/`ape-core` (or `venv ape`)
+pub fn main(args: &[String]) -> Result<()> {
+    let (matches, globals) = cli::parse_command(args, OPTIONS, 1, "ape --help")?;
 
     // Show help
-    if config.help {
+    if matches.flag(HELP.long) {
         show_help();
-        return;
+        return Ok(());
     }
 
-    // Handle clean mode
-    if config.clean {
-        let cache_file = get_cache_path();
-        print_info("Removing cache file...", config.no_color);
-        if cache_file.exists() {
-            match fs::remove_file(&cache_file) {
-                Ok(_) => {
-                    print_success(
-                        &format!("Cache file removed successfully: {}", cache_file.display()),
-                        config.no_color,
-                    );
-                }
-                Err(e) => {
-                    print_error(
-                        &format!(
-                            "Failed to remove cache file at {}: {}",
-                            cache_file.display(),
-                            e
-                        ),
-                        config.no_color,
-                    );
-                }
-            }
-        } else {
-            print_info(
-                &format!("Cache file does not exist: {}", cache_file.display()),
-                config.no_color,
-            );
-        }
-        println!();
-        return;
+    // Print shell wrapper functions
+    if let Ok(Some(shell)) = cli::shell_value(&matches, INIT.long) {
+        crate::init(shell);
+        return Ok(());
     }
 
-    let cache_file = get_cache_path();
-    let predefined_dirs = get_search_dirs();
-
-    if config.verbose {
-        print_debug("Verbose mode enabled.", config.no_color);
-        print_debug("Directories to be scanned:", config.no_color);
-        for dir in &predefined_dirs {
-            println!("  {}", dir.display());
-        }
-        println!();
-    }
+    // Handle clean mode
+    if matches.flag(CLEAN.long) {
+        return crate::clean(&globals);
+    }
+
+    activate(
+        matches.positionals.first().map(String::as_str),
+        matches.flag(SCAN.long) || matches.flag(FULL.long),
+        matches.flag(FULL.long),
+        matches.flag(FORCE.long),
+        &globals,
+    )
+}
+
+/// Finds `env_name` and activates it, optionally rescanning first. Without
+/// a name (or with `.`) the current project's environment is activated; with
+/// `scan` and no name, only the scan results are shown; `full` makes the
+/// scan read every directory again. Broken environments are refused unless
+/// `force` is set.
+pub fn activate(
+    env_name: Option<&str>,
+    scan: bool,
+    full: bool,
+    force: bool,
+    globals: &Globals,
+) -> Result<()> {
+    let opts = globals.options();
+    let cache_file = get_cache_path()?;
+    migrate_legacy_cache(&cache_file, &opts);
 
-    // Handle scan mode
-    if config.scan {
-        print_info("Performing comprehensive scan...", config.no_color);
-        print_info("This may take a moment...", config.no_color);
-        println!();
+    let predefined_dirs = get_search_dirs()?;
 
-        let environments = scan_all_venvs(&config);
-        print_success(
-            &format!("Found {} environments.", environments.len()),
-            config.no_color,
-        );
+    print_search_dirs(&predefined_dirs, &opts);
 
-        if let Err(e) = save_cache(&cache_file, &environments, &config) {
-            print_warning(&format!("Failed to save cache: {}", e), config.no_color);
-        } else {
-            print_success("Cache updated.", config.no_color);
-        }
-        println!();
+    // Handle scan mode
+    if scan {
+        let environments = rescan(&cache_file, full, &opts)?;
 
         // If no environment name provided, just show results
-        if config.env_name.is_none() {
+        if env_name.is_none() {
             if !environments.is_empty() {
-                println!();
-                println!("Found environments:");
-                println!();
+                outln!();
+                outln!("Found environments:");
+                outln!();
                 print_scan_results(&environments);
-                println!();
+                outln!();
             }
-            println!("Run 'ape <env_name>' to activate an environment.");
-            return;
+            outln!("Run 'ape <env_name>' to activate an environment.");
+            return Ok(());
         }
-    }
-
-    // Check if environment name provided
-    let env_name = match &config.env_name {
-        Some(name) => name,
-        None => {
-            print_error("No environment name specified.", config.no_color);
-            eprintln!();
-            eprintln!("Usage: ape [OPTIONS] <env_name>");
-            eprintln!("       ape --help for more information");
-            return;
+    } else {
+        crate::refresh_cache(&cache_file, &opts)?;
+    }
+
+    // No name (or `.`): the environment of the project we are standing in
+    let env_name = match env_name {
+        Some(name) if name != "." => name,
+        _ => {
+            let env = find_local(&cache_file, &predefined_dirs, globals)?;
+            ensure_healthy(&env, force, &opts)?;
+            activate_environment(&env, globals.shell, &opts)?;
+            record_use(&cache_file, &env, &opts);
+            return Ok(());
         }
     };
 
-    if config.verbose {
+    if globals.verbose {
         print_debug(
             &format!("Searching for environment: {}", env_name),
-            config.no_color,
+            globals.no_color,
         );
-        println!();
+        outln!();
     }
 
     // Try to find the environment
-    let found_env = find_environment(env_name, &cache_file, &predefined_dirs, &config);
-
-    match found_env {
-        Some(env) => activate_environment(&env, &config),
-        None => {
-            print_error(
-                &format!("Environment \"{}\" not found.", env_name),
-                config.no_color,
-            );
-            eprintln!();
-            if cache_file.exists() {
-                eprintln!(
-                    "Tip: Try running 'ape --scan' to update the cache and find new environments."
-                );
-            } else {
-                eprintln!("Tip: Try running 'ape --scan' to perform a comprehensive search.");
-            }
-            eprintln!("     Or use 'spe' to see all available environments.");
-        }
-    }
-}
-
-fn parse_args() -> Config {
-    let args: Vec<String> = env::args().collect();
-    let mut config = Config {
-        help: false,
-        verbose: false,
-        scan: false,
-        clean: false,
-        no_color: false,
-        env_name: None,
-        unknown_flag: None,
-    };
-
-    let mut i = 1; // Skip program name
-    let mut env_arg_candidates: Vec<String> = Vec::new();
-
-    while i < args.len() {
-        let arg = &args[i];
-        match arg.as_str() {
-            "-h" | "--help" | "/?" => config.help = true,
-            "-v" | "--verbose" => config.verbose = true,
-            "-s" | "--scan" => config.scan = true,
-            "-c" | "--clean" => config.clean = true,
-            "--no-color" => config.no_color = true,
-            _ => {
-                if arg.starts_with('-') {
-                    // Unknown flag
-                    if config.unknown_flag.is_none() {
-                        config.unknown_flag = Some(arg.clone());
-                    }
-                } else {
-                    // Potential environment name
-                    env_arg_candidates.push(arg.clone());
-                }
-            }
-        }
-        i += 1;
-    }
-
-    // The environment name is the first non-flag argument
-    if !env_arg_candidates.is_empty() {
-        config.env_name = Some(env_arg_candidates[0].clone());
-    }
-
-    config
-}
-
-fn get_cache_path() -> PathBuf {
-    let temp_dir = env::temp_dir();
-    temp_dir.join(CACHE_FILENAME)
-}
-
-fn get_config_path() -> PathBuf {
-    let user_profile = env::var("USERPROFILE").unwrap_or_else(|_| String::from("."));
-    PathBuf::from(user_profile)
-        .join(".config")
-        .join(CONFIG_FILENAME)
-}
-
-fn load_user_config() -> Option<UserConfig> {
-    let config_path = get_config_path();
-    if !config_path.exists() {
-        return None;
-    }
-
-    match fs::read_to_string(&config_path) {
-        Ok(contents) => match toml::from_str(&contents) {
-            Ok(config) => Some(config),
-            Err(_) => None,
-        },
-        Err(_) => None,
-    }
-}
-
-fn get_search_dirs() -> Vec<PathBuf> {
-    // First, try to load custom directories from config file
-    if let Some(user_config) = load_user_config() {
-        if let Some(dirs) = user_config.directories {
-            let custom_dirs: Vec<PathBuf> = dirs
-                .iter()
-                .map(|d| {
-                    let expanded = d.replace(
-                        "%USERPROFILE%",
-                        &env::var("USERPROFILE").unwrap_or_default(),
-                    );
-                    PathBuf::from(expanded)
-                })
-                .collect();
-
-            if !custom_dirs.is_empty() {
-                return custom_dirs;
-            }
-        }
-    }
-
-    // Fall back to predefined directories
-    get_predefined_dirs()
-}
-
-fn get_predefined_dirs() -> Vec<PathBuf> {
-    let user_profile = match env::var("USERPROFILE") {
-        Ok(path) => PathBuf::from(path),
-        Err(_) => return Vec::new(),
-    };
-
-    vec![
-        user_profile.clone(),
-        user_profile.join("code"),
-        user_profile.join("dev"),
-        user_profile.join("projects"),
-        user_profile.join("AppData").join("Local").join("Programs"),
-    ]
-}
-
-fn scan_all_venvs(config: &Config) -> Vec<Environment> {
-    let user_profile = match env::var("USERPROFILE") {
-        Ok(path) => PathBuf::from(path),
-        Err(_) => {
-            eprintln!("Error: Could not determine USERPROFILE");
-            return Vec::new();
-        }
-    };
-
-    if config.verbose {
-        print_debug(
-            "Scanning for pyvenv.cfg files in user directory...",
-            config.no_color,
-        );
-        print_debug(
-            "Using parallel scanning for maximum speed...",
-            config.no_color,
-        );
-    } else {
-        println!("Scanning...");
-    }
-
-    // Use walkdir with parallel processing for much faster scanning
-    let pyvenv_files: Vec<PathBuf> = WalkDir::new(&user_profile)
-        .follow_links(false)
-        .into_iter()
-        .filter_entry(|e| {
-            // Skip excluded directories
-            if let Some(name) = e.file_name().to_str() {
-                let name_lower = name.to_lowercase();
-                !name_lower.contains("temp")
-                    && !name_lower.contains("cache")
-                    && !name_lower.contains("tmp")
-                    && name != "node_modules"
-                    && name != "$RECYCLE.BIN"
-                    && name != "System Volume Information"
-            } else {
-                true
-            }
-        })
-        .filter_map(|e| e.ok())
-        .filter(|e| e.file_name() == "pyvenv.cfg")
-        .map(|e| e.path().to_path_buf())
-        .collect();
-
-    let scan_count = pyvenv_files.len();
-
-    if config.verbose {
-        print_debug(
-            &format!("Found {} pyvenv.cfg files, processing...", scan_count),
-            config.no_color,
-        );
-    }
-
-    // Process pyvenv.cfg files in parallel
-    let environments: Vec<Environment> = pyvenv_files
-        .par_iter()
-        .filter_map(|cfg_path| {
-            if let Some(parent) = cfg_path.parent() {
-                // Skip if in excluded paths (double check)
-                let path_str = parent.to_string_lossy().to_lowercase();
-                if path_str.contains("\\temp\\")
-                    || path_str.contains("\\cache\\")
-                    || path_str.contains("\\tmp\\")
-                    || path_str.contains("node_modules")
-                {
-                    return None;
-                }
-
-                if let Some(env) = detect_environment_at_path(parent) {
-                    if config.verbose {
-                        print_debug(
-                            &format!(
-                                "Found: {} ({}) at {}",
-                                env.name,
-                                env.env_type,
-                                env.path.display()
-                            ),
-                            config.no_color,
-                        );
-                    }
-                    return Some(env);
-                }
-            }
-            None
-        })
-        .collect();
-
-    if config.verbose {
-        print_debug(
-            &format!("Scan complete. Found {} environments.", environments.len()),
-            config.no_color,
-        );
-    } else {
-        print_info(
-            &format!("Scan complete. Checked {} files.", scan_count),
-            config.no_color,
-        );
-    }
-
-    environments
-}
-
-fn detect_environment_at_path(path: &Path) -> Option<Environment> {
-    let activate_script = path.join("Scripts").join("activate.bat");
-    if !activate_script.exists() {
-        return None;
-    }
-
-    let name = path.file_name()?.to_string_lossy().to_string();
-    let env_type = detect_env_type(path);
-
-    Some(Environment {
-        name,
-        env_type,
-        path: path.to_path_buf(),
-    })
-}
-
-fn detect_env_type(path: &Path) -> String {
-    // Check for conda
-    if path.join("conda-meta").exists() {
-        return "conda".to_string();
-    }
-
-    // Check for uv
-    let pyvenv_cfg = path.join("pyvenv.cfg");
-    if pyvenv_cfg.exists() {
-        if let Ok(contents) = fs::read_to_string(&pyvenv_cfg) {
-            if contents.contains("uv") {
-                return "uv".to_string();
-            }
-        }
-    }
-
-    // Default to venv
-    if path.join("Scripts").join("activate.bat").exists() {
-        return "venv".to_string();
-    }
-
-    "unknown".to_string()
-}
-
-fn load_cache(cache_file: &Path, config: &Config) -> io::Result<Vec<Environment>> {
-    if config.verbose {
-        print_debug("Loading cache...", config.no_color);
-    }
-
-    let contents = fs::read_to_string(cache_file)?;
-    let environments: Vec<Environment> = serde_json::from_str(&contents)
-        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
-
-    if config.verbose {
-        print_debug(
-            &format!("Loaded {} environments from cache", environments.len()),
-            config.no_color,
-        );
-    }
-
-    Ok(environments)
-}
-
-fn save_cache(cache_file: &Path, environments: &[Environment], config: &Config) -> io::Result<()> {
-    if config.verbose {
-        print_debug(
-            &format!("Saving cache to {}", cache_file.display()),
-            config.no_color,
-        );
-        print_debug(
-            &format!("Number of environments to save: {}", environments.len()),
-            config.no_color,
-        );
-    }
-
-    let json = serde_json::to_string_pretty(environments)
-        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
-
-    fs::write(cache_file, json)?;
-
-    if config.verbose {
-        print_debug("Cache saved successfully", config.no_color);
-    }
-
+    let env = find_environment(env_name, &cache_file, &predefined_dirs, &opts)?;
+    ensure_healthy(&env, force, &opts)?;
+    activate_environment(&env, globals.shell, &opts)?;
+    record_use(&cache_file, &env, &opts);
     Ok(())
 }
 
-fn find_environment(
-    env_name: &str,
+/// Walks up from the current directory, then falls back to looking up the
+/// current directory's name like any other environment name
+fn find_local(
     cache_file: &Path,
-    predefined_dirs: &[PathBuf],
-    config: &Config,
-) -> Option<Environment> {
-    // Try cache first
-    if cache_file.exists() {
-        if config.verbose {
-            print_debug("Checking cache...", config.no_color);
-        }
-        if let Ok(environments) = load_cache(cache_file, config) {
-            for env in environments {
-                if env.name.eq_ignore_ascii_case(env_name) {
-                    let activate_script = env.path.join("Scripts").join("activate.bat");
-                    if activate_script.exists() {
-                        if config.verbose {
-                            print_debug(
-                                &format!(
-                                    "Found in cache: {} ({}) at {}",
-                                    env.name,
-                                    env.env_type,
-                                    env.path.display()
-                                ),
-                                config.no_color,
-                            );
-                        }
-                        return Some(env);
-                    } else if config.verbose {
-                        print_debug(
-                            "Cached path no longer valid, searching directories...",
-                            config.no_color,
-                        );
-                    }
-                }
-            }
-        }
-    }
-
-    // Search predefined directories recursively
-    if config.verbose {
-        print_debug("Searching predefined directories...", config.no_color);
+    search_dirs: &[SearchDir],
+    globals: &Globals,
+) -> Result<Environment> {
+    let opts = globals.options();
+    let cwd = env::current_dir()?;
+    if globals.verbose {
+        print_debug(
+            &format!("Searching for the environment of {}", cwd.display()),
+            globals.no_color,
+        );
     }
 
-    const MAX_SEARCH_DEPTH: usize = 4;
-
-    for dir in predefined_dirs {
-        if !dir.exists() {
-            if config.verbose {
+    match find_project_environment(&cwd, cache_file, &opts) {
+        Err(Error::NotFound { name, .. }) => {
+            if globals.verbose {
                 print_debug(
-                    &format!("Directory not found: \"{}\"", dir.display()),
-                    config.no_color,
+                    &format!("No project environment found, looking up \"{}\"", name),
+                    globals.no_color,
                 );
             }
-            continue;
-        }
-
-        if config.verbose {
-            print_debug(&format!("Searching \"{}\" (depth {})...", dir.display(), MAX_SEARCH_DEPTH), config.no_color);
-        }
-
-        for entry in WalkDir::new(dir)
-            .max_depth(MAX_SEARCH_DEPTH)
-            .follow_links(false)
-            .into_iter()
-            .filter_entry(|e| {
-                if let Some(name) = e.file_name().to_str() {
-                    let n = name.to_lowercase();
-                    !n.contains("temp") && !n.contains("cache") && !n.contains("tmp")
-                        && name != "node_modules" && name != "$RECYCLE.BIN"
-                        && name != "System Volume Information"
-                } else {
-                    true
-                }
-            })
-        {
-            let entry = match entry {
-                Ok(e) => e,
-                Err(_) => continue,
-            };
-            if !entry.file_type().is_dir() {
-                continue;
-            }
-            if let Some(name) = entry.path().file_name() {
-                if name.to_string_lossy().eq_ignore_ascii_case(env_name) {
-                    if let Some(env) = detect_environment_at_path(entry.path()) {
-                        if config.verbose {
-                            print_debug(
-                                &format!(
-                                    "Found {} environment at: {}",
-                                    env.env_type,
-                                    entry.path().display()
-                                ),
-                                config.no_color,
-                            );
-                        }
-                        return Some(env);
-                    }
-                }
-            }
+            find_environment(&name, cache_file, search_dirs, &opts)
         }
+        result => result,
     }
-
-    None
 }
 
-fn activate_environment(env: &Environment, config: &Config) {
-    let activate_script = env.path.join("Scripts").join("activate.bat");
-
-    if !activate_script.exists() {
-        print_error(
-            &format!(
-                "Activation script not found at \"{}\"",
-                activate_script.display()
-            ),
-            config.no_color,
-        );
-        return;
-    }
-
-    if config.verbose {
-        print_debug(
-            &format!("Activating: {}", env.path.display()),
-            config.no_color,
-        );
-        print_debug(&format!("Type: {}", env.env_type), config.no_color);
-        print_debug(
-            &format!("Activation script: {}", activate_script.display()),
-            config.no_color,
-        );
-        println!();
-    }
-
-    print_success(
-        &format!("Activating \"{}\" ({})...", env.name, env.env_type),
-        config.no_color,
-    );
-    println!();
-
-    // Write venv path to marker file for shell wrapper to pick up
-    let marker_file = env::temp_dir().join("_venv_activate_path.txt");
-    if let Err(e) = fs::write(&marker_file, env.path.to_string_lossy().as_ref()) {
-        print_error(
-            &format!("Failed to write activation marker file: {}", e),
-            config.no_color,
-        );
-        return;
-    }
-
-    if config.verbose {
-        print_debug(
-            &format!("Wrote activation path to: {}", marker_file.display()),
-            config.no_color,
-        );
-    }
-
-    print_info(
-        &format!(
-            "[{} ready - Type 'deactivate' to deactivate the environment]",
-            env.name
-        ),
-        config.no_color,
-    );
-    println!();
-}
-
-fn print_scan_results(environments: &[Environment]) {
+pub fn print_scan_results(environments: &[Environment]) {
     for (i, env) in environments.iter().enumerate() {
-        println!("  {}. {} ({})", i + 1, env.name, env.env_type);
-        println!("     {}", env.path.display());
-        println!();
-    }
-}
-
-fn print_error(msg: &str, no_color: bool) {
-    if no_color {
-        eprintln!("Error: {}", msg);
-    } else {
-        eprintln!("{} {}", "Error:".red().bold(), msg);
-    }
-}
-
-fn print_warning(msg: &str, no_color: bool) {
-    if no_color {
-        eprintln!("Warning: {}", msg);
-    } else {
-        eprintln!("{} {}", "Warning:".yellow().bold(), msg);
-    }
-}
-
-fn print_success(msg: &str, no_color: bool) {
-    if no_color {
-        println!("{}", msg);
-    } else {
-        println!("{}", msg.green());
-    }
-}
-
-fn print_info(msg: &str, no_color: bool) {
-    if no_color {
-        println!("{}", msg);
-    } else {
-        println!("{}", msg.cyan());
-    }
-}
-
-fn print_debug(msg: &str, no_color: bool) {
-    if no_color {
-        println!("[DEBUG] {}", msg);
-    } else {
-        println!("{}", format!("[DEBUG] {}", msg).dimmed());
+        outln!("  {}. {} ({})", i + 1, env.name, env.env_type);
+        outln!("     {}", env.path.display());
+        outln!();
     }
 }
 
 fn show_help() {
-    println!();
-    println!("APE - Activate Python Environment");
-    println!("==================================");
-    println!();
-    println!("DESCRIPTION:");
-    println!("  Quickly activate a Python virtual environment by name.");
-    println!("  Searches predefined directories for the specified environment.");
-    println!();
-    println!("USAGE:");
-    println!("  ape [OPTIONS] <env_name>");
-    println!("  ape --scan [OPTIONS]");
-    println!();
-    println!("ARGUMENTS:");
-    println!("  env_name         Name of the environment to activate");
-
-    println!();
-    println!("OPTIONS:");
-    println!("  -h, --help       Show this help message and exit");
-    println!("  -v, --verbose    Enable verbose output (shows debug information)");
-    println!("  -s, --scan       Perform comprehensive scan and update cache");
-    println!("  -c, --clean      Remove the cache file and exit");
-    println!("  --no-color       Disable colored output");
-    println!();
-    println!("BEHAVIOR:");
-    println!("  Searches for the specified environment using cached results (if available),");
-    println!("  or searches predefined directories. With --scan, performs a comprehensive");
-    println!("  search of your entire user folder and updates the persistent cache.");
-    println!();
-    println!("  Activates the environment in your current shell session (CMD or PowerShell).");
-    println!("  Type 'deactivate' to return to normal.");
-    println!();
-    println!("SEARCHED DIRECTORIES:");
-    println!("  Each directory is searched recursively (up to 4 levels deep):");
-    println!("  - %USERPROFILE%");
-    println!("  - %USERPROFILE%\\code");
-    println!("  - %USERPROFILE%\\dev");
-    println!("  - %USERPROFILE%\\projects");
-    println!("  - %USERPROFILE%\\AppData\\Local\\Programs");
-    println!();
-    println!("SUPPORTED ENVIRONMENT TYPES:");
-    println!("  - venv   : Standard Python virtual environments");
-    println!("  - conda  : Anaconda/Miniconda environments");
-    println!("  - uv     : UV-created virtual environments");
-    println!();
-    println!("EXAMPLES:");
-    println!("  ape myenv              Activate environment named 'myenv'");
-    println!("  ape -s                 Scan entire user folder and update cache");
-    println!("  ape --scan             Scan entire user folder and update cache (same as -s)");
-    println!("  ape -s myenv           Scan and then activate 'myenv'");
-    println!("  ape -v finance         Activate 'finance' with debug output");
-    println!("  ape -c                 Remove the cache file");
-    println!("  ape --clean            Remove the cache file (same as -c)");
-    println!("  ape --help             Show this help message");
-    println!();
-    println!("CACHE:");
-    println!("  - Cache location: %TEMP%\\python_venv_cache.json");
-    println!("  - Run 'ape --scan' after creating new venvs to update cache");
-    println!("  - Delete cache file to force directory search");
-    println!("  - Cache persists until manually deleted");
-    println!();
-    println!("CUSTOM DIRECTORIES:");
-    println!("  Create a config file at: %USERPROFILE%\\.config\\python_venv_config.toml");
-    println!();
-    println!("  Example config:");
-    println!("  directories = [");
-    println!("      \"%USERPROFILE%\\\\projects\",");
-    println!("      \"C:\\\\dev\\\\python\",");
-    println!("      \"%USERPROFILE%\\\\code\\\\.venvs\"");
-    println!("  ]");
-    println!();
-    println!("NOTES:");
-    println!("  - Type 'deactivate' to deactivate the environment");
-    println!("  - Use 'spe' to interactively browse all available environments");
-    println!("  - First time use: Run 'ape --scan' to build the cache for faster searches");
-    println!("  - Requires shell wrappers (ape.cmd for CMD, PowerShell function for pwsh)");
-    println!();
+    outln!();
+    outln!("APE - Activate Python Environment");
+    outln!("==================================");
+    outln!();
+    outln!("DESCRIPTION:");
+    outln!("  Quickly activate a Python virtual environment by name.");
+    outln!("  Searches predefined directories for the specified environment.");
+    outln!();
+    outln!("USAGE:");
+    outln!("  ape [OPTIONS] [env_name]");
+    outln!("  ape --scan [OPTIONS]");
+    outln!();
+    outln!("ARGUMENTS:");
+    outln!("  env_name         Name of the environment to activate. Without a name (or");
+    outln!("                   with '.') the environment of the current project is used");
+    outln!("                   (see PROJECTS below). Environments in");
+    outln!("                   .venv, venv or env folders are named after their project");
+    outln!("                   (or the pyvenv.cfg prompt); use 'project/.venv' when a");
+    outln!("                   name matches several environments. A unique prefix,");
+    outln!("                   substring or subsequence of a name also works");
+
+    outln!();
+    crate::show_options_help(OPTIONS);
+    outln!("BEHAVIOR:");
+    outln!("  Searches for the specified environment using cached results (if available),");
+    outln!("  or searches predefined directories. With --scan, performs a comprehensive");
+    outln!("  search of your entire user folder and updates the persistent cache.");
+    outln!("  Later scans only read directories that changed since; --full reads all.");
+    outln!();
+    outln!("  Activates the environment in your current shell session (CMD, PowerShell,");
+    outln!("  bash, zsh, fish or nu).");
+    outln!("  Type 'deactivate' to return to normal.");
+    outln!();
+    outln!("  An environment whose base interpreter, python executable or site-packages");
+    outln!("  is gone is reported as broken and not activated unless --force is given.");
+    outln!();
+    outln!("PROJECTS:");
+    outln!("  'ape' and 'ape .' walk up from the current directory and activate the first");
+    outln!("  environment found: the directory itself if it is one, the name in a");
+    outln!("  .python-venv or .python-version file, a .venv, venv or env folder, or the");
+    outln!("  cached environment of a pyproject.toml project. Otherwise the current");
+    outln!("  directory's name is looked up like any other name.");
+    outln!();
+    crate::show_search_help();
+    outln!("EXAMPLES:");
+    outln!("  ape myenv              Activate environment named 'myenv'");
+    outln!("  ape                    Activate the environment of the current project");
+    outln!("  ape -s                 Scan entire user folder and update cache");
+    outln!("  ape --scan             Scan entire user folder and update cache (same as -s)");
+    outln!("  ape -s myenv           Scan and then activate 'myenv'");
+    outln!("  ape --full             Scan reading every directory again");
+    outln!("  ape -v finance         Activate 'finance' with debug output");
+    outln!("  ape billing/.venv      Activate the .venv inside the 'billing' project");
+    outln!("  ape fin                Activate 'finance' if no other name starts with 'fin'");
+    outln!("  ape -f oldenv          Activate 'oldenv' even though it is broken");
+    outln!("  ape -c                 Remove the cache file");
+    outln!("  ape --clean            Remove the cache file (same as -c)");
+    outln!("  ape --help             Show this help message");
+    outln!();
+    crate::show_files_help("ape");
+    outln!("NOTES:");
+    outln!("  - Type 'deactivate' to deactivate the environment");
+    outln!("  - Use 'spe' to interactively browse all available environments");
+    outln!("  - First time use: Run 'ape --scan' to build the cache for faster searches");
+    outln!("  - Requires shell wrappers (ape.cmd for CMD, PowerShell function for pwsh)");
+    outln!("  - bash/zsh: add 'eval \"$(venv init zsh)\"' to your shell rc file");
+    outln!("  - fish: add 'venv init fish | source' to config.fish");
+    outln!("  - 'ape' is the same as 'venv activate' (see 'venv help')");
+    outln!();
 }
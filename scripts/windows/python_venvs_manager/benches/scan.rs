@@ -0,0 +1,154 @@
+//! Home-folder walk benchmark on a synthetic tree
+//!
+//! Generates `<projects>` projects (300 by default), each with some source
+//! folders, a `.git` folder and a `.venv` holding 40 packages in
+//! `site-packages`, then times the single-threaded `WalkDir` walk `--scan`
+//! used before against [`venv_core::index::walk`] with one thread, with
+//! every CPU, and again with the index of a previous walk. All runs are
+//! warm: the first one fills the OS cache.
+//!
+//! ```text
+//! cargo bench --bench scan -- 1000
+//! ```
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::thread;
+use std::time::{Duration, Instant};
+use venv_core::config::SearchDir;
+use venv_core::exclude::Excludes;
+use venv_core::index::{ScanIndex, scan_threads, walk};
+use walkdir::WalkDir;
+
+const RUNS: usize = 5;
+const PACKAGES: usize = 40;
+
+fn main() {
+    let projects = env::args()
+        .skip(1)
+        .find_map(|arg| arg.parse().ok())
+        .unwrap_or(300);
+    let root = env::temp_dir().join(format!("venv-scan-bench-{}", process::id()));
+
+    println!("Generating {} projects in {}...", projects, root.display());
+    let dirs = generate(&root, projects).expect("failed to generate the tree");
+    println!("{} directories", dirs);
+    // Directories modified within 2 s of a walk are not trusted by the next
+    thread::sleep(Duration::from_millis(2100));
+
+    // The default exclude rules only
+    let excludes = Excludes::new(&root, &[], Path::new("-"), false).expect("default rules");
+    let dir = SearchDir {
+        path: root.clone(),
+        depth: None,
+        follow_symlinks: false,
+    };
+    let threads = scan_threads();
+
+    let before = bench("walkdir, 1 thread (before)", || walkdir_count(&root));
+    let one = bench("walk, 1 thread", || {
+        walk(&dir, &dir.path, &[], &excludes, 1).found.len()
+    });
+    let all = bench(&format!("walk, {} workers", threads), || {
+        walk(&dir, &dir.path, &[], &excludes, threads).found.len()
+    });
+    let previous: [ScanIndex; 1] = [walk(&dir, &dir.path, &[], &excludes, threads).index];
+    let incremental = bench(&format!("walk, {} workers, unchanged", threads), || {
+        walk(&dir, &dir.path, &previous, &excludes, threads)
+            .found
+            .len()
+    });
+
+    println!();
+    for (label, time) in [
+        ("1 thread", one),
+        ("all workers", all),
+        ("unchanged", incremental),
+    ] {
+        println!(
+            "speedup over walkdir, {:12} {:6.1}x",
+            label,
+            before.as_secs_f64() / time.as_secs_f64()
+        );
+    }
+
+    if let Err(e) = fs::remove_dir_all(&root) {
+        eprintln!("Could not remove {}: {}", root.display(), e);
+    }
+}
+
+/// Runs `f` [`RUNS`] times, printing and returning the fastest time
+fn bench(label: &str, f: impl Fn() -> usize) -> Duration {
+    let mut best = Duration::MAX;
+    let mut found = 0;
+    for _ in 0..RUNS {
+        let started = Instant::now();
+        found = f();
+        best = best.min(started.elapsed());
+    }
+    println!(
+        "{:32} {:>10.2?}  ({} pyvenv.cfg/pyproject.toml)",
+        label, best, found
+    );
+    best
+}
+
+/// The markers the old scan collected, walking every directory
+fn walkdir_count(root: &Path) -> usize {
+    WalkDir::new(root)
+        .follow_links(false)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_name() == "pyvenv.cfg" || e.file_name() == "pyproject.toml")
+        .count()
+}
+
+/// Creates the tree, returning how many directories it holds
+fn generate(root: &Path, projects: usize) -> std::io::Result<usize> {
+    let mut dirs: Vec<PathBuf> = Vec::new();
+    for p in 0..projects {
+        let project = root
+            .join(format!("group{}", p % 10))
+            .join(format!("project{}", p));
+        for sub in [
+            "src/app/core",
+            "src/app/api",
+            "tests/unit",
+            "docs",
+            ".git/objects/ab",
+        ] {
+            dirs.push(project.join(sub));
+        }
+        let venv = project.join(".venv");
+        dirs.push(venv.join("bin"));
+        dirs.push(venv.join("include"));
+        let site_packages = venv.join("lib").join("python3.12").join("site-packages");
+        for i in 0..PACKAGES {
+            let package = site_packages.join(format!("package{}", i));
+            dirs.push(package.join("__pycache__"));
+            dirs.push(package.join("sub").join("__pycache__"));
+            dirs.push(site_packages.join(format!("package{}-1.0.dist-info", i)));
+        }
+    }
+    for dir in &dirs {
+        fs::create_dir_all(dir)?;
+    }
+    for p in 0..projects {
+        let project = root
+            .join(format!("group{}", p % 10))
+            .join(format!("project{}", p));
+        fs::write(project.join("pyproject.toml"), "[project]\n")?;
+        fs::write(
+            project.join(".venv").join("pyvenv.cfg"),
+            "home = /usr/bin\nversion = 3.12.1\n",
+        )?;
+        fs::write(project.join(".venv").join("bin").join("activate"), "")?;
+    }
+    Ok(WalkDir::new(root)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_dir())
+        .count())
+}
@@ -22,7 +22,7 @@ cargo --version
 echo.
 
 :: Build both programs
-echo Building APE-CORE and SPE-CORE...
+echo Building VENV...
 echo.
 cargo build --release
 if %errorlevel% neq 0 (
@@ -81,26 +81,28 @@ echo Installing files to: !TARGET_DIR!
 echo.
 
 :: Clean up old files (both old and new naming)
-for %%F in (ape-core.exe spe-core.exe ape.exe spe.exe ape.cmd spe.cmd Invoke-PythonVenv.ps1) do (
+for %%F in (venv.exe ape-core.exe spe-core.exe ape.exe spe.exe ape.cmd spe.cmd Invoke-PythonVenv.ps1) do (
     if exist "!TARGET_DIR!\%%F" del "!TARGET_DIR!\%%F" 2>nul
 )
 
-:: Create hard links for core binaries
-set "LINK_OK=1"
-mklink /H "!TARGET_DIR!\ape-core.exe" "%~dp0target\release\ape-core.exe" >nul 2>&1
+:: Install venv.exe, and link ape-core.exe and spe-core.exe to it (the binary
+:: picks its behavior from the name it was invoked as)
+mklink /H "!TARGET_DIR!\venv.exe" "%~dp0target\release\venv.exe" >nul 2>&1
 if %errorlevel% equ 0 (
-    echo   [OK] ape-core.exe (hard link)
+    echo   [OK] venv.exe (hard link)
 ) else (
-    echo   [FALLBACK] Copying ape-core.exe...
-    copy /Y "%~dp0target\release\ape-core.exe" "!TARGET_DIR!\ape-core.exe" >nul
+    echo   [FALLBACK] Copying venv.exe...
+    copy /Y "%~dp0target\release\venv.exe" "!TARGET_DIR!\venv.exe" >nul
 )
 
-mklink /H "!TARGET_DIR!\spe-core.exe" "%~dp0target\release\spe-core.exe" >nul 2>&1
-if %errorlevel% equ 0 (
-    echo   [OK] spe-core.exe (hard link)
-) else (
-    echo   [FALLBACK] Copying spe-core.exe...
-    copy /Y "%~dp0target\release\spe-core.exe" "!TARGET_DIR!\spe-core.exe" >nul
+for %%N in (ape-core spe-core) do (
+    mklink /H "!TARGET_DIR!\%%N.exe" "!TARGET_DIR!\venv.exe" >nul 2>&1
+    if !errorlevel! equ 0 (
+        echo   [OK] %%N.exe (hard link to venv.exe^)
+    ) else (
+        echo   [FALLBACK] Copying venv.exe to %%N.exe...
+        copy /Y "!TARGET_DIR!\venv.exe" "!TARGET_DIR!\%%N.exe" >nul
+    )
 )
 
 :: Copy wrapper scripts
@@ -161,7 +163,7 @@ echo.
 echo Installed to: !TARGET_DIR!
 echo.
 echo What was set up:
-echo   - Core binaries: ape-core.exe, spe-core.exe
+echo   - Core binary: venv.exe (also as ape-core.exe, spe-core.exe)
 echo   - CMD wrappers: ape.cmd, spe.cmd (work in cmd.exe)
 echo   - PowerShell wrapper: Invoke-PythonVenv.ps1
 echo   - User PATH: updated
@@ -0,0 +1,277 @@
+//! Command-line parsing shared by the `venv`, `ape` and `spe` front-ends
+//!
+//! Supports `--flag`, `--flag value`, `--flag=value`, short flags (`-v`),
+//! clustered short flags (`-vs`), `/?` for help and `--` to end options.
+//! Every problem is collected so all of them can be reported at once.
+
+use venv_core::output::{print_error, print_warning, reserve_stdout};
+use venv_core::{Error, Options, Shell};
+
+/// An option a command accepts
+pub struct Opt {
+    pub long: &'static str,
+    pub short: Option<char>,
+    pub takes_value: bool,
+}
+
+pub const HELP: Opt = Opt {
+    long: "help",
+    short: Some('h'),
+    takes_value: false,
+};
+pub const VERBOSE: Opt = Opt {
+    long: "verbose",
+    short: Some('v'),
+    takes_value: false,
+};
+pub const NO_COLOR: Opt = Opt {
+    long: "no-color",
+    short: None,
+    takes_value: false,
+};
+pub const SHELL: Opt = Opt {
+    long: "shell",
+    short: None,
+    takes_value: true,
+};
+pub const SCAN: Opt = Opt {
+    long: "scan",
+    short: Some('s'),
+    takes_value: false,
+};
+pub const FULL: Opt = Opt {
+    long: "full",
+    short: None,
+    takes_value: false,
+};
+pub const FORCE: Opt = Opt {
+    long: "force",
+    short: Some('f'),
+    takes_value: false,
+};
+pub const CLEAN: Opt = Opt {
+    long: "clean",
+    short: Some('c'),
+    takes_value: false,
+};
+pub const INIT: Opt = Opt {
+    long: "init",
+    short: None,
+    takes_value: true,
+};
+pub const JSON: Opt = Opt {
+    long: "json",
+    short: None,
+    takes_value: false,
+};
+pub const DAYS: Opt = Opt {
+    long: "days",
+    short: None,
+    takes_value: true,
+};
+
+/// Every option any command knows, used to find the subcommand word
+pub const ALL: &[&Opt] = &[
+    &HELP, &VERBOSE, &NO_COLOR, &SHELL, &SCAN, &FULL, &FORCE, &CLEAN, &INIT, &JSON, &DAYS,
+];
+
+/// Result of parsing: options in the order given, then positional arguments
+#[derive(Debug, Default)]
+pub struct Matches {
+    options: Vec<(&'static str, Option<String>)>,
+    pub positionals: Vec<String>,
+}
+
+impl Matches {
+    pub fn flag(&self, long: &str) -> bool {
+        self.options.iter().any(|(name, _)| *name == long)
+    }
+
+    /// Value of the last occurrence of `--long`
+    pub fn value(&self, long: &str) -> Option<&str> {
+        self.options
+            .iter()
+            .rev()
+            .find(|(name, _)| *name == long)
+            .and_then(|(_, value)| value.as_deref())
+    }
+
+    /// Errors for options that were recognized but are not valid for `command`
+    pub fn disallowed(&self, allowed: &[&Opt], command: &str) -> Vec<String> {
+        let mut errors = Vec::new();
+        for (name, _) in &self.options {
+            if !allowed.iter().any(|o| o.long == *name) {
+                let error = format!("Flag \"--{}\" is not valid for '{}'", name, command);
+                if !errors.contains(&error) {
+                    errors.push(error);
+                }
+            }
+        }
+        errors
+    }
+
+    /// Errors for positional arguments beyond the first `max`
+    pub fn extra_positionals(&self, max: usize) -> Vec<String> {
+        self.positionals
+            .iter()
+            .skip(max)
+            .map(|arg| format!("Unexpected argument \"{}\"", arg))
+            .collect()
+    }
+}
+
+/// Matches `args` against `spec`, returning what was recognized along with
+/// every error, so callers can add their own before reporting them
+pub fn parse(args: &[String], spec: &[&Opt]) -> (Matches, Vec<String>) {
+    let mut matches = Matches::default();
+    let mut errors = Vec::new();
+    let mut iter = args.iter();
+
+    while let Some(arg) = iter.next() {
+        if arg == "--" {
+            matches.positionals.extend(iter.by_ref().cloned());
+            break;
+        }
+        if arg == "/?" {
+            matches.options.push((HELP.long, None));
+            continue;
+        }
+
+        if let Some(long) = arg.strip_prefix("--") {
+            let (name, inline) = match long.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (long, None),
+            };
+            match spec.iter().find(|o| o.long == name) {
+                None => errors.push(format!("Unknown flag \"--{}\"", name)),
+                Some(opt) if opt.takes_value => match inline.or_else(|| iter.next().cloned()) {
+                    Some(value) => matches.options.push((opt.long, Some(value))),
+                    None => errors.push(format!("Flag \"--{}\" requires a value", name)),
+                },
+                Some(opt) if inline.is_some() => {
+                    errors.push(format!("Flag \"--{}\" does not take a value", opt.long))
+                }
+                Some(opt) => matches.options.push((opt.long, None)),
+            }
+        } else if let Some(shorts) = arg.strip_prefix('-').filter(|s| !s.is_empty()) {
+            for (i, c) in shorts.char_indices() {
+                match spec.iter().find(|o| o.short == Some(c)) {
+                    None => errors.push(format!("Unknown flag \"-{}\"", c)),
+                    Some(opt) if opt.takes_value => {
+                        // The rest of the cluster, or the next argument, is the value
+                        let rest = &shorts[i + c.len_utf8()..];
+                        let value = if rest.is_empty() {
+                            iter.next().cloned()
+                        } else {
+                            Some(rest.to_string())
+                        };
+                        match value {
+                            Some(value) => matches.options.push((opt.long, Some(value))),
+                            None => errors.push(format!("Flag \"-{}\" requires a value", c)),
+                        }
+                        break;
+                    }
+                    Some(opt) => matches.options.push((opt.long, None)),
+                }
+            }
+        } else {
+            matches.positionals.push(arg.clone());
+        }
+    }
+
+    (matches, errors)
+}
+
+/// Options common to every command
+pub struct Globals {
+    pub verbose: bool,
+    pub no_color: bool,
+    /// Set with `--shell`: print activation code instead of using `APE_HANDOFF`
+    pub shell: Option<Shell>,
+}
+
+impl Globals {
+    /// Reads the common options and reserves stdout if `--shell` was given
+    pub fn from_matches(matches: &Matches) -> Result<Globals, String> {
+        let globals = Globals {
+            verbose: matches.flag(VERBOSE.long),
+            no_color: matches.flag(NO_COLOR.long),
+            shell: shell_value(matches, SHELL.long)?,
+        };
+        if globals.shell.is_some() {
+            reserve_stdout();
+        }
+        Ok(globals)
+    }
+
+    pub fn options(&self) -> Options {
+        Options {
+            verbose: self.verbose,
+            no_color: self.no_color,
+        }
+    }
+}
+
+/// Parses the value of a shell-valued option (`--shell`, `--init`)
+pub fn shell_value(matches: &Matches, long: &str) -> Result<Option<Shell>, String> {
+    match matches.value(long) {
+        None => Ok(None),
+        Some(value) => Shell::from_name(value).map(Some).ok_or_else(|| {
+            format!(
+                "Unsupported shell \"{}\" (expected bash, zsh, fish or nu)",
+                value
+            )
+        }),
+    }
+}
+
+/// Parses the arguments of one command, allowing at most `max_positionals`
+/// positional arguments. Every problem found ends up in one [`Error::Usage`].
+pub fn parse_command(
+    args: &[String],
+    spec: &[&Opt],
+    max_positionals: usize,
+    help_hint: &str,
+) -> venv_core::Result<(Matches, Globals)> {
+    let (matches, mut errors) = parse(args, spec);
+    errors.extend(matches.extra_positionals(max_positionals));
+    finish(matches, errors, help_hint)
+}
+
+/// Adds the errors of the global options to those of [`parse`] and the
+/// caller, failing with all of them in one [`Error::Usage`]
+pub fn finish(
+    matches: Matches,
+    mut errors: Vec<String>,
+    help_hint: &str,
+) -> venv_core::Result<(Matches, Globals)> {
+    let usage = |errors: Vec<String>| Error::Usage {
+        errors,
+        help_hint: help_hint.to_string(),
+    };
+    if let Err(e) = shell_value(&matches, INIT.long) {
+        errors.push(e);
+    }
+    match Globals::from_matches(&matches) {
+        Ok(globals) if errors.is_empty() => Ok((matches, globals)),
+        Ok(_) => Err(usage(errors)),
+        Err(e) => {
+            errors.push(e);
+            Err(usage(errors))
+        }
+    }
+}
+
+/// Prints every usage error followed by a pointer to `help_hint`
+pub fn report_usage_errors(errors: &[String], help_hint: &str, no_color: bool) {
+    eprintln!();
+    for error in errors {
+        if error.starts_with("Unsupported shell") {
+            print_error(error, no_color);
+        } else {
+            print_warning(error, no_color);
+        }
+    }
+    eprintln!("Run '{}' for usage information.", help_hint);
+    eprintln!();
+}
@@ -0,0 +1,12 @@
+# python_venv.fish
+# fish wrapper functions for APE and SPE
+#
+# Usage: add ONE of the following lines to ~/.config/fish/config.fish:
+#   venv init fish | source
+#   source /path/to/python_venv.fish
+#
+# The venv binary prints activation code on stdout (all messages go to
+# stderr), which the functions below source in the current shell session.
+
+function ape; command venv ape --shell fish $argv | source; return $pipestatus[1]; end
+function spe; command venv spe --shell fish $argv | source; return $pipestatus[1]; end
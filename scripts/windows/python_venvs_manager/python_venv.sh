@@ -0,0 +1,13 @@
+# python_venv.sh
+# bash/zsh wrapper functions for APE and SPE
+#
+# Usage: add ONE of the following lines to ~/.bashrc or ~/.zshrc:
+#   eval "$(venv init zsh)"
+#   source /path/to/python_venv.sh
+#
+# The venv binary prints activation code on stdout (all messages go to
+# stderr), which the functions below eval in the current shell session.
+# A failed lookup returns the binary's exit code without eval'ing anything.
+
+ape() { local code; code="$(command venv ape --shell bash "$@")" || return; eval "$code"; }
+spe() { local code; code="$(command venv spe --shell bash "$@")" || return; eval "$code"; }
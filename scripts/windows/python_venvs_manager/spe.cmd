@@ -1,9 +1,31 @@
 @echo off
 setlocal enabledelayedexpansion
-set "_MARKER=%TEMP%\_venv_activate_path.txt"
-if exist "!_MARKER!" del "!_MARKER!"
+:: Private handoff file for this invocation only: mkdir fails if the name
+:: is taken, so no other process can share or pre-plant it. A missing,
+:: unwritable or full %TEMP% fails every attempt, so give up after a few
+set "_HANDOFF_TRIES=0"
+:_pick_handoff
+set /a _HANDOFF_TRIES+=1
+if !_HANDOFF_TRIES! gtr 10 (
+    echo Error: Could not create a handoff folder in "%TEMP%" 1>&2
+    exit /b 7
+)
+set "_HANDOFF_DIR=%TEMP%\_venv_activate_%RANDOM%%RANDOM%"
+mkdir "!_HANDOFF_DIR!" 2>nul || goto _pick_handoff
+set "APE_HANDOFF=!_HANDOFF_DIR!\path.txt"
+type nul > "!APE_HANDOFF!"
 "%~dp0spe-core.exe" %*
-if not exist "!_MARKER!" goto :eof
-set /p "_VENV_PATH="<"!_MARKER!"
-del "!_MARKER!"
-endlocal & call "%_VENV_PATH%\Scripts\activate.bat"
+set "_VENV_RC=!errorlevel!"
+set "_VENV_PATH="
+set "_CONDA_ROOT="
+:: Line 1 is the environment, line 2 the conda installation activating it
+for /f "usebackq delims=" %%L in ("!APE_HANDOFF!") do (
+    if not defined _VENV_PATH (set "_VENV_PATH=%%L") else set "_CONDA_ROOT=%%L"
+)
+rd /s /q "!_HANDOFF_DIR!" 2>nul
+if not defined _VENV_PATH exit /b !_VENV_RC!
+if defined _CONDA_ROOT (
+    endlocal & call "%_CONDA_ROOT%\Scripts\activate.bat" "%_VENV_PATH%"
+) else (
+    endlocal & call "%_VENV_PATH%\Scripts\activate.bat"
+)
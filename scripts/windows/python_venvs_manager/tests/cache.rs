@@ -0,0 +1,141 @@
+//! Concurrent access to the cache file from several threads, standing in
+//! for parallel ape and spe processes
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+use venv_core::Options;
+use venv_core::cache::{
+    CACHE_VERSION, CacheFile, CacheHeader, load_cache, lock_cache, save_cache, try_lock_cache,
+};
+use venv_core::environment::Environment;
+
+const WRITERS: usize = 4;
+const READERS: usize = 4;
+const ROUNDS: usize = 50;
+
+/// A fresh directory for one test, removed when dropped
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(name: &str) -> TempDir {
+        let dir = env::temp_dir().join(format!("venv-cache-test-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).expect("failed to create the test directory");
+        TempDir(dir)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// A cache of `count` environments that need not exist
+fn cache_of(root: &Path, count: usize) -> CacheFile {
+    let environments = (0..count)
+        .map(|i| {
+            serde_json::from_value::<Environment>(serde_json::json!({
+                "name": format!("env{:03}", i),
+                "env_type": "venv",
+                "path": root.join(format!("env{:03}", i)),
+            }))
+            .expect("valid environment")
+        })
+        .collect();
+    CacheFile {
+        header: CacheHeader {
+            version: CACHE_VERSION,
+            tool_version: "test".to_string(),
+            created: 0,
+            scan_roots: vec![root.to_path_buf()],
+            config_hash: String::new(),
+            scan_ms: 0,
+        },
+        environments,
+    }
+}
+
+#[test]
+fn readers_never_see_a_partial_cache() {
+    let dir = TempDir::new("readers");
+    let cache_file = dir.0.join("python_venv_cache.json");
+    let quiet = Options::default();
+    // Writers only ever write one of these sizes
+    let sizes = |writer: usize| 10 + writer * 100;
+    save_cache(&cache_file, &cache_of(&dir.0, sizes(0)), &quiet).expect("initial save");
+
+    let done = AtomicBool::new(false);
+    let reads = AtomicUsize::new(0);
+    thread::scope(|scope| {
+        let writers: Vec<_> = (0..WRITERS)
+            .map(|writer| {
+                let (cache_file, root) = (&cache_file, &dir.0);
+                scope.spawn(move || {
+                    let cache = cache_of(root, sizes(writer));
+                    for _ in 0..ROUNDS {
+                        let _lock = lock_cache(cache_file, &quiet).expect("lock");
+                        save_cache(cache_file, &cache, &quiet).expect("save");
+                    }
+                })
+            })
+            .collect();
+        for _ in 0..READERS {
+            scope.spawn(|| {
+                while !done.load(Ordering::Relaxed) {
+                    let environments = load_cache(&cache_file, &quiet).expect("complete cache");
+                    assert!(
+                        (0..WRITERS).any(|w| environments.len() == sizes(w)),
+                        "read {} environments",
+                        environments.len()
+                    );
+                    reads.fetch_add(1, Ordering::Relaxed);
+                }
+            });
+        }
+        for writer in writers {
+            writer.join().expect("writer panicked");
+        }
+        done.store(true, Ordering::Relaxed);
+    });
+
+    assert!(reads.load(Ordering::Relaxed) > 0);
+    // No temporary files are left behind
+    let leftovers: Vec<_> = fs::read_dir(&dir.0)
+        .expect("test directory")
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_name().to_string_lossy().ends_with(".tmp"))
+        .collect();
+    assert!(leftovers.is_empty(), "left behind: {:?}", leftovers);
+}
+
+#[test]
+fn writers_hold_the_lock_one_at_a_time() {
+    let dir = TempDir::new("lock");
+    let cache_file = dir.0.join("python_venv_cache.json");
+    let quiet = Options::default();
+    let inside = AtomicBool::new(false);
+
+    thread::scope(|scope| {
+        for _ in 0..WRITERS {
+            scope.spawn(|| {
+                for _ in 0..ROUNDS / 5 {
+                    let _lock = lock_cache(&cache_file, &quiet).expect("lock");
+                    assert!(!inside.swap(true, Ordering::SeqCst), "two writers at once");
+                    thread::sleep(Duration::from_millis(1));
+                    inside.store(false, Ordering::SeqCst);
+                }
+            });
+        }
+    });
+
+    let held = lock_cache(&cache_file, &quiet).expect("lock");
+    assert!(try_lock_cache(&cache_file).expect("lock file").is_none());
+    drop(held);
+    assert!(try_lock_cache(&cache_file).expect("lock file").is_some());
+}
@@ -0,0 +1,50 @@
+//! Usage errors are reported all at once, whichever kinds they are
+
+use std::process::{Command, Output};
+
+fn venv(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_venv"))
+        .args(args)
+        .arg("--no-color")
+        .output()
+        .expect("failed to run venv")
+}
+
+fn assert_usage_errors(output: &Output, expected: &[&str]) {
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(2), "{}", stderr);
+    for error in expected {
+        assert!(
+            stderr.contains(error),
+            "missing {:?} in:\n{}",
+            error,
+            stderr
+        );
+    }
+}
+
+#[test]
+fn unknown_flags_and_extra_arguments_together() {
+    let output = venv(&["ape", "--bogus", "a", "b", "-q"]);
+    assert_usage_errors(
+        &output,
+        &[
+            "Unknown flag \"--bogus\"",
+            "Unknown flag \"-q\"",
+            "Unexpected argument \"b\"",
+        ],
+    );
+}
+
+#[test]
+fn subcommand_errors_together() {
+    let output = venv(&["list", "--bogus", "--json", "extra"]);
+    assert_usage_errors(
+        &output,
+        &[
+            "Unknown flag \"--bogus\"",
+            "Flag \"--json\" is not valid for 'list'",
+            "Unexpected argument \"extra\"",
+        ],
+    );
+}
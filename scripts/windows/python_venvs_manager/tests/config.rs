@@ -0,0 +1,36 @@
+//! Errors in `python_venv_config.toml` point at the entry that caused them
+
+use std::path::Path;
+use venv_core::Error;
+use venv_core::config::{DirectoryEntry, parse_user_config};
+
+const CONFIG: &str = r#"# Search these instead of the predefined directories
+directories = [
+    "~",
+    "~/code",
+    { path = "~/work", depth = 2 },
+    { path = "~/projects", depht = 6 },
+]
+"#;
+
+#[test]
+fn misspelled_directory_key_is_located() {
+    let error = parse_user_config(Path::new("config.toml"), CONFIG).expect_err("invalid config");
+    let Error::Config { line, message, .. } = error else {
+        panic!("expected a config error, got {:?}", error);
+    };
+    assert_eq!(line, Some(6), "{}", message);
+    assert!(message.contains("unknown field `depht`"), "{}", message);
+}
+
+#[test]
+fn paths_and_tables_mix() {
+    let fixed = CONFIG.replace("depht", "depth");
+    let config = parse_user_config(Path::new("config.toml"), &fixed).expect("valid config");
+    let directories = config.directories.expect("directories");
+    assert!(matches!(&directories[1], DirectoryEntry::Path(path) if path == "~/code"));
+    assert!(matches!(
+        &directories[3],
+        DirectoryEntry::Table(table) if table.path == "~/projects" && table.depth == Some(6)
+    ));
+}
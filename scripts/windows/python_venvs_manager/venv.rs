@@ -0,0 +1,713 @@
+//! `venv`: one binary for every Python environment command
+//!
+//! Invoked as `ape`/`ape-core` or `spe`/`spe-core` (via a hardlink, symlink or
+//! copy) it behaves like the old standalone tools. Otherwise the first
+//! positional argument selects a subcommand.
+//!
+//! Every failure is printed here and mapped to the exit code of its
+//! [`Error`] variant, so scripts can tell "not found" from a broken config.
+
+mod ape;
+mod cli;
+mod spe;
+
+use cli::{DAYS, FORCE, FULL, Globals, HELP, JSON, NO_COLOR, Opt, SCAN, SHELL, VERBOSE};
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::{self, ExitCode, Stdio};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use venv_core::cache::{
+    cache_status, clean_cache, get_cache_path, load_cache, migrate_legacy_cache,
+    refresh_stale_cache, rescan, try_lock_cache,
+};
+use venv_core::config::{get_config_path, get_predefined_dirs, get_search_dirs, print_search_dirs};
+use venv_core::discovery::MAX_SEARCH_DEPTH;
+use venv_core::doctor::{Check, DEFAULT_UNUSED_DAYS, DoctorOptions, diagnose};
+use venv_core::environment::unix_now;
+use venv_core::output::{print_debug, print_error};
+use venv_core::{Error, Options, Result, Shell, outln};
+
+/// Set for the `venv scan` a background refresh starts, so it scans whatever
+/// name this binary was invoked by
+const BACKGROUND_SCAN_VAR: &str = "APE_BACKGROUND_SCAN";
+
+/// A `venv` subcommand
+struct Command {
+    name: &'static str,
+    /// Positional arguments, as shown in the usage line
+    args: &'static str,
+    summary: &'static str,
+    options: &'static [&'static Opt],
+    max_args: usize,
+}
+
+const COMMANDS: &[Command] = &[
+    Command {
+        name: "activate",
+        args: "[env_name]",
+        summary: "Activate an environment by name (same as 'ape')",
+        options: &[&HELP, &VERBOSE, &NO_COLOR, &SHELL, &SCAN, &FULL, &FORCE],
+        max_args: 1,
+    },
+    Command {
+        name: "select",
+        args: "",
+        summary: "List environments and pick one to activate (same as 'spe')",
+        options: &[&HELP, &VERBOSE, &NO_COLOR, &SHELL, &SCAN, &FULL, &FORCE],
+        max_args: 0,
+    },
+    Command {
+        name: "list",
+        args: "",
+        summary: "List known environments",
+        options: &[&HELP, &VERBOSE, &NO_COLOR, &SCAN, &FULL],
+        max_args: 0,
+    },
+    Command {
+        name: "scan",
+        args: "",
+        summary: "Scan the whole user folder and update the cache",
+        options: &[&HELP, &VERBOSE, &NO_COLOR, &FULL],
+        max_args: 0,
+    },
+    Command {
+        name: "doctor",
+        args: "",
+        summary: "Audit cached environments and suggest fixes",
+        options: &[&HELP, &VERBOSE, &NO_COLOR, &JSON, &DAYS],
+        max_args: 0,
+    },
+    Command {
+        name: "cache",
+        args: "[status]",
+        summary: "Show the cache's age, size, scan duration and roots",
+        options: &[&HELP, &NO_COLOR, &JSON],
+        max_args: 1,
+    },
+    Command {
+        name: "watch",
+        args: "",
+        summary: "Keep the cache up to date as environments come and go",
+        options: &[&HELP, &VERBOSE, &NO_COLOR],
+        max_args: 0,
+    },
+    Command {
+        name: "clean",
+        args: "",
+        summary: "Remove the cache file",
+        options: &[&HELP, &VERBOSE, &NO_COLOR],
+        max_args: 0,
+    },
+    Command {
+        name: "init",
+        args: "<shell>",
+        summary: "Print the ape/spe wrapper functions for bash, zsh, fish or nu",
+        options: &[&HELP],
+        max_args: 1,
+    },
+    Command {
+        name: "help",
+        args: "[command]",
+        summary: "Show help for venv or one of its commands",
+        options: &[&HELP, &NO_COLOR],
+        max_args: 1,
+    },
+];
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().collect();
+    let rest = args.get(1..).unwrap_or_default();
+
+    let result = if env::var_os(BACKGROUND_SCAN_VAR).is_some() {
+        run(&["scan".to_string()])
+    } else {
+        match args.first().and_then(|arg0| personality(arg0)) {
+            Some(Personality::Ape) => ape::main(rest),
+            Some(Personality::Spe) => spe::main(rest),
+            None => run(rest),
+        }
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            report(&e, rest.iter().any(|a| a == "--no-color"));
+            ExitCode::from(e.exit_code())
+        }
+    }
+}
+
+/// Prints `error` with whatever hints help the user recover from it
+fn report(error: &Error, no_color: bool) {
+    match error {
+        Error::Usage { errors, help_hint } => {
+            cli::report_usage_errors(errors, help_hint, no_color);
+        }
+        Error::NotFound { suggestions, .. } => {
+            print_error(&error.to_string(), no_color);
+            eprintln!();
+            if !suggestions.is_empty() {
+                eprintln!("Did you mean:");
+                for name in suggestions {
+                    eprintln!("  {}", name);
+                }
+                return;
+            }
+            if get_cache_path().is_ok_and(|path| path.exists()) {
+                eprintln!(
+                    "Tip: Try running 'ape --scan' to update the cache and find new environments."
+                );
+            } else {
+                eprintln!("Tip: Try running 'ape --scan' to perform a comprehensive search.");
+            }
+            eprintln!("     Or use 'spe' to see all available environments.");
+        }
+        Error::Ambiguous { candidates, .. } => {
+            print_error(&error.to_string(), no_color);
+            eprintln!();
+            // Plain names tell the candidates apart unless they share one
+            let distinct = candidates
+                .iter()
+                .all(|a| candidates.iter().filter(|b| b.name == a.name).count() == 1);
+            let label = |env: &venv_core::Environment| {
+                if distinct {
+                    env.name.clone()
+                } else {
+                    env.qualified_name()
+                }
+            };
+            for env in candidates {
+                eprintln!("  {:24} {}", label(env), env.path.display());
+            }
+            eprintln!();
+            eprintln!(
+                "Tip: Use one of the names above, e.g. 'ape {}'.",
+                label(&candidates[0])
+            );
+        }
+        Error::Broken { name, problems, .. } => {
+            print_error(&error.to_string(), no_color);
+            eprintln!();
+            for problem in problems {
+                eprintln!("  - {}", problem);
+            }
+            eprintln!();
+            eprintln!(
+                "Tip: Recreate the environment, or use 'ape --force {}' to activate it anyway.",
+                name
+            );
+        }
+        _ => print_error(&error.to_string(), no_color),
+    }
+}
+
+enum Personality {
+    Ape,
+    Spe,
+}
+
+/// Which legacy tool argv[0] names, if any (`ape`, `ape-core.exe`, ...)
+fn personality(arg0: &str) -> Option<Personality> {
+    let stem = Path::new(arg0)
+        .file_stem()?
+        .to_string_lossy()
+        .to_lowercase();
+    match stem.strip_suffix("-core").unwrap_or(&stem) {
+        "ape" => Some(Personality::Ape),
+        "spe" => Some(Personality::Spe),
+        _ => None,
+    }
+}
+
+fn run(args: &[String]) -> Result<()> {
+    // `venv ape ...` / `venv spe ...` keep the legacy command lines working
+    match args.first().map(String::as_str) {
+        Some("ape") => return ape::main(&args[1..]),
+        Some("spe") => return spe::main(&args[1..]),
+        _ => {}
+    }
+
+    let (matches, mut errors) = cli::parse(args, cli::ALL);
+
+    let Some(name) = matches.positionals.first() else {
+        cli::finish(matches, errors, "venv help")?;
+        show_help();
+        return Ok(());
+    };
+    let Some(command) = find_command(name) else {
+        errors.push(format!("Unknown command \"{}\"", name));
+        return Err(Error::Usage {
+            errors,
+            help_hint: "venv help".to_string(),
+        });
+    };
+
+    errors.extend(matches.disallowed(command.options, command.name));
+    errors.extend(matches.extra_positionals(command.max_args + 1));
+    let (matches, globals) = cli::finish(matches, errors, &format!("venv help {}", command.name))?;
+
+    let arg = matches.positionals.get(1).map(String::as_str);
+    if matches.flag(HELP.long) {
+        show_command_help(command);
+        return Ok(());
+    }
+
+    let full = matches.flag(FULL.long);
+    let scan = matches.flag(SCAN.long) || full;
+    let force = matches.flag(FORCE.long);
+    match command.name {
+        "activate" => ape::activate(arg, scan, full, force, &globals),
+        "select" => spe::select(scan, full, force, &globals),
+        "list" => list(scan, full, &globals),
+        "scan" => {
+            let opts = globals.options();
+            let cache_file = get_cache_path()?;
+            migrate_legacy_cache(&cache_file, &opts);
+            print_search_dirs(&get_search_dirs()?, &opts);
+            let environments = rescan(&cache_file, full, &opts)?;
+            ape::print_scan_results(&environments);
+            Ok(())
+        }
+        "doctor" => doctor(matches.flag(JSON.long), matches.value(DAYS.long), &globals),
+        "cache" => match arg {
+            None | Some("status") => status(matches.flag(JSON.long)),
+            Some(other) => Err(Error::usage(
+                format!("Unknown cache command \"{}\"", other),
+                "venv help cache",
+            )),
+        },
+        "watch" => watch(&globals),
+        "clean" => clean(&globals),
+        "init" => {
+            let value = arg.ok_or_else(|| Error::usage("Missing shell name", "venv help init"))?;
+            let shell = Shell::from_name(value).ok_or_else(|| {
+                Error::usage(
+                    format!(
+                        "Unsupported shell \"{}\" (expected bash, zsh, fish or nu)",
+                        value
+                    ),
+                    "venv help init",
+                )
+            })?;
+            init(shell);
+            Ok(())
+        }
+        "help" => {
+            match arg {
+                None => show_help(),
+                Some(name) => show_command_help(find_command(name).ok_or_else(|| {
+                    Error::usage(format!("Unknown command \"{}\"", name), "venv help")
+                })?),
+            }
+            Ok(())
+        }
+        _ => unreachable!("every command in COMMANDS is dispatched"),
+    }
+}
+
+fn find_command(name: &str) -> Option<&'static Command> {
+    COMMANDS.iter().find(|c| c.name.eq_ignore_ascii_case(name))
+}
+
+/// `venv list`: prints the environment table without prompting
+fn list(scan: bool, full: bool, globals: &Globals) -> Result<()> {
+    let environments = spe::load_environments(scan, full, globals)?;
+    if environments.is_empty() {
+        outln!("No Python environments found.");
+        outln!();
+    } else {
+        spe::print_table(&environments);
+    }
+    Ok(())
+}
+
+/// `venv doctor`: audits the cached environments and prints what is wrong
+/// with them, as text or as JSON
+fn doctor(json: bool, days: Option<&str>, globals: &Globals) -> Result<()> {
+    let opts = globals.options();
+    let unused_days = match days {
+        None => DEFAULT_UNUSED_DAYS,
+        Some(value) => value.parse().map_err(|_| {
+            Error::usage(
+                format!("Invalid number of days \"{}\"", value),
+                "venv help doctor",
+            )
+        })?,
+    };
+
+    let cache_file = get_cache_path()?;
+    migrate_legacy_cache(&cache_file, &opts);
+    if !cache_file.exists() {
+        outln!("No cache yet. Run 'venv scan' to find your environments first.");
+        return Ok(());
+    }
+    let cached = load_cache(&cache_file, &opts)?;
+    let options = DoctorOptions {
+        unused_days,
+        search_dirs: get_search_dirs()?,
+        config_path: get_config_path()?,
+        now: unix_now(),
+    };
+    let report = diagnose(&cached, &options);
+
+    if json {
+        let text = serde_json::to_string_pretty(&report)
+            .map_err(|e| Error::Io(std::io::Error::other(e)))?;
+        outln!("{}", text);
+        return Ok(());
+    }
+
+    outln!();
+    if report.findings.is_empty() {
+        outln!(
+            "Checked {} environments, no problems found.",
+            report.environments
+        );
+        outln!();
+        return Ok(());
+    }
+    outln!(
+        "Checked {} environments, found {} problems.",
+        report.environments,
+        report.findings.len()
+    );
+    for check in Check::ALL {
+        let findings: Vec<_> = report
+            .findings
+            .iter()
+            .filter(|f| f.check == check)
+            .collect();
+        if findings.is_empty() {
+            continue;
+        }
+        outln!();
+        outln!("{} ({}):", check.title(), findings.len());
+        for finding in findings {
+            outln!("  {}: {}", finding.name, finding.message);
+            for path in &finding.paths {
+                outln!("    {}", path.display());
+            }
+            outln!("    Fix: {}", finding.fix);
+        }
+    }
+    outln!();
+    Ok(())
+}
+
+/// `venv cache status`: when the cache was made, from what, and whether it
+/// is due for a refresh
+fn status(json: bool) -> Result<()> {
+    let cache_file = get_cache_path()?;
+    migrate_legacy_cache(&cache_file, &Options::default());
+    if !cache_file.exists() {
+        outln!("No cache yet. Run 'venv scan' to find your environments first.");
+        return Ok(());
+    }
+    let status = cache_status(&cache_file)?;
+    if json {
+        let text = serde_json::to_string_pretty(&status)
+            .map_err(|e| Error::Io(std::io::Error::other(e)))?;
+        outln!("{}", text);
+        return Ok(());
+    }
+
+    let header = &status.header;
+    outln!();
+    outln!("Cache:        {}", status.path.display());
+    outln!(
+        "Format:       {} (written by {})",
+        header.version,
+        if header.tool_version.is_empty() {
+            "an older version"
+        } else {
+            &header.tool_version
+        }
+    );
+    let due = match status.max_age {
+        Some(max_age) if status.age > max_age => " (refresh due)".to_string(),
+        Some(max_age) => format!(" (refreshed after {})", format_duration(max_age)),
+        None => " (never refreshed automatically)".to_string(),
+    };
+    outln!("Scanned:      {} ago{}", format_duration(status.age), due);
+    if header.scan_ms > 0 {
+        outln!("Scan took:    {:.2} s", header.scan_ms as f64 / 1000.0);
+    }
+    outln!("Environments: {}", status.environments);
+    outln!("Scan roots:");
+    for root in &header.scan_roots {
+        outln!("  {}", root.display());
+    }
+    if status.stale {
+        outln!("Settings changed since the scan; the next ape or spe rescans.");
+    }
+    if status.scanning {
+        outln!("A scan is running.");
+    }
+    outln!();
+    Ok(())
+}
+
+/// `45 s`, `12 min`, `5 h 3 min`, `1 day`, `2 days 4 h`
+fn format_duration(secs: u64) -> String {
+    let (mins, hours, days) = (secs / 60, secs / 3600, secs / 86400);
+    let (big, small) = if days > 0 {
+        let unit = if days == 1 { "day" } else { "days" };
+        (format!("{} {}", days, unit), format!("{} h", hours % 24))
+    } else if hours > 0 {
+        (format!("{} h", hours), format!("{} min", mins % 60))
+    } else if mins > 0 {
+        return format!("{} min", mins);
+    } else {
+        return format!("{} s", secs);
+    };
+    if small.starts_with("0 ") {
+        big
+    } else {
+        format!("{} {}", big, small)
+    }
+}
+
+/// Brings the cache up to date before ape or spe use it: rescans if the
+/// search settings changed, and refreshes an expired cache in the background
+/// while this run uses it as is
+pub fn refresh_cache(cache_file: &Path, opts: &Options) -> Result<()> {
+    if refresh_stale_cache(cache_file, opts)? {
+        start_background_scan(cache_file, opts);
+    }
+    Ok(())
+}
+
+/// Starts `venv scan` in a detached process that outlives this one, unless
+/// a scan is running already
+fn start_background_scan(cache_file: &Path, opts: &Options) {
+    // The lock is released again right away; the scan takes it itself
+    if !matches!(try_lock_cache(cache_file), Ok(Some(_))) {
+        if opts.verbose {
+            print_debug("A scan is already running", opts.no_color);
+        }
+        return;
+    }
+    let exe = match env::current_exe() {
+        Ok(exe) => exe,
+        Err(e) => {
+            if opts.verbose {
+                print_debug(
+                    &format!("Cannot start a background scan: {}", e),
+                    opts.no_color,
+                );
+            }
+            return;
+        }
+    };
+
+    let mut command = process::Command::new(exe);
+    command
+        .env(BACKGROUND_SCAN_VAR, "1")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    detach(&mut command);
+    match command.spawn() {
+        Ok(child) if opts.verbose => print_debug(
+            &format!(
+                "Refreshing the cache in the background (pid {})",
+                child.id()
+            ),
+            opts.no_color,
+        ),
+        Err(e) if opts.verbose => print_debug(
+            &format!("Cannot start a background scan: {}", e),
+            opts.no_color,
+        ),
+        _ => {}
+    }
+}
+
+/// Keeps `command` running when the terminal it was started from sends
+/// Ctrl+C or closes
+#[cfg(unix)]
+fn detach(command: &mut process::Command) {
+    use std::os::unix::process::CommandExt;
+    command.process_group(0);
+}
+
+#[cfg(windows)]
+fn detach(command: &mut process::Command) {
+    use std::os::windows::process::CommandExt;
+    const DETACHED_PROCESS: u32 = 0x0000_0008;
+    const CREATE_NEW_PROCESS_GROUP: u32 = 0x0000_0200;
+    command.creation_flags(DETACHED_PROCESS | CREATE_NEW_PROCESS_GROUP);
+}
+
+/// `--init` / `venv init`: prints the wrapper functions for `shell`
+pub fn init(shell: Shell) {
+    print!("{}", shell.init_script());
+}
+
+/// `venv watch`: keeps the cache current until interrupted with Ctrl+C or
+/// terminated
+fn watch(globals: &Globals) -> Result<()> {
+    let opts = globals.options();
+    let cache_file = get_cache_path()?;
+    migrate_legacy_cache(&cache_file, &opts);
+    print_search_dirs(&get_search_dirs()?, &opts);
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let flag = Arc::clone(&stop);
+    ctrlc::set_handler(move || flag.store(true, Ordering::Relaxed))
+        .map_err(|e| Error::Io(std::io::Error::other(e)))?;
+    venv_core::watch::watch(&cache_file, &stop, &opts)
+}
+
+/// `--clean` / `venv clean`: removes the cache file
+pub fn clean(globals: &Globals) -> Result<()> {
+    let opts = globals.options();
+    let cache_file = get_cache_path()?;
+    migrate_legacy_cache(&cache_file, &opts);
+    clean_cache(&cache_file, &opts)
+}
+
+/// `path` for help texts, or why it cannot be determined
+pub fn display_path(path: Result<PathBuf>) -> String {
+    match path {
+        Ok(path) => path.display().to_string(),
+        Err(e) => e.to_string(),
+    }
+}
+
+/// The OPTIONS section of a help text
+pub fn show_options_help(options: &[&Opt]) {
+    outln!("OPTIONS:");
+    for opt in options {
+        outln!("  {}", option_help(opt));
+    }
+    outln!();
+}
+
+/// The SEARCHED DIRECTORIES and SUPPORTED ENVIRONMENT TYPES sections of the
+/// ape and spe help texts
+pub fn show_search_help() {
+    outln!("SEARCHED DIRECTORIES:");
+    outln!(
+        "  Each directory is searched recursively (up to {} levels deep, unless configured):",
+        MAX_SEARCH_DEPTH
+    );
+    let dirs = get_predefined_dirs();
+    if dirs.is_empty() {
+        outln!("  (none: the home folder cannot be found)");
+    }
+    for dir in dirs {
+        outln!("  - {}", dir.display());
+    }
+    outln!("  Folders named node_modules, .git, cache, temp, tmp and the like are skipped;");
+    outln!("  add gitignore-style rules with 'exclude' in the config file (-v shows why).");
+    outln!("  'directories' in the config file replaces this list; entries may set depth,");
+    outln!("  recursive and follow_symlinks, and 'scan_roots' replaces the home folder as");
+    outln!("  what --scan walks.");
+    outln!();
+    outln!("SUPPORTED ENVIRONMENT TYPES:");
+    outln!("  - venv   : Standard Python virtual environments");
+    outln!("  - conda  : conda, mamba and micromamba environments");
+    outln!("  - uv     : UV-created virtual environments");
+    outln!("  - poetry, pipenv, pdm, hatch, rye, pixi : project manager environments");
+    outln!();
+}
+
+/// The CACHE, CUSTOM DIRECTORIES and EXIT CODES sections of the help text
+/// of `tool` (ape or spe)
+pub fn show_files_help(tool: &str) {
+    outln!("CACHE:");
+    outln!("  - Cache location: {}", display_path(get_cache_path()));
+    outln!("  - Override with the APE_CACHE environment variable (full file path)");
+    outln!(
+        "  - Run '{} --scan' after creating new venvs to update cache",
+        tool
+    );
+    outln!("  - Delete cache file to force directory search");
+    outln!("  - A cache older than cache_max_age_hours (default 24) is refreshed in the");
+    outln!("    background while it is used; 'venv cache status' shows its age");
+    outln!();
+    outln!("CUSTOM DIRECTORIES:");
+    outln!(
+        "  Create a config file at: {}",
+        display_path(get_config_path())
+    );
+    outln!("  (override with the APE_CONFIG environment variable)");
+    outln!();
+    outln!("  Example config:");
+    outln!("  directories = [");
+    outln!("      \"~/projects\",");
+    outln!("      {{ path = \"~/code/.venvs\", depth = 1 }},");
+    outln!("  ]");
+    outln!();
+    show_exit_codes();
+}
+
+fn show_exit_codes() {
+    outln!("EXIT CODES:");
+    outln!("  0 success, 2 usage error, 3 not found, 4 ambiguous name,");
+    outln!("  5 config error, 6 cache error, 7 other I/O error, 8 broken environment");
+    outln!();
+}
+
+fn option_help(opt: &Opt) -> &'static str {
+    match opt.long {
+        "help" => "-h, --help       Show this help message and exit",
+        "verbose" => "-v, --verbose    Enable verbose output (shows debug information)",
+        "no-color" => "--no-color       Disable colored output",
+        "shell" => "--shell <shell>  Print activation code for bash, zsh, fish or nu on stdout",
+        "scan" => "-s, --scan       Perform comprehensive scan and update cache first",
+        "full" => "--full           Scan reading every directory, not only changed ones",
+        "force" => "-f, --force      Activate even if the environment is broken",
+        "clean" => "-c, --clean      Remove the cache file and exit",
+        "init" => "--init <shell>   Print the ape/spe wrapper functions",
+        "json" => "--json           Print the report as JSON",
+        "days" => "--days <n>       Report environments unused for more than n days (default 90)",
+        _ => "",
+    }
+}
+
+fn show_command_help(command: &Command) {
+    outln!();
+    outln!("USAGE:");
+    outln!("  venv {} [OPTIONS] {}", command.name, command.args);
+    outln!();
+    outln!("  {}", command.summary);
+    outln!();
+    show_options_help(command.options);
+}
+
+fn show_help() {
+    outln!();
+    outln!("VENV - Python Environment Tools");
+    outln!("===============================");
+    outln!();
+    outln!("USAGE:");
+    outln!("  venv <command> [OPTIONS] [ARGS]");
+    outln!();
+    outln!("COMMANDS:");
+    for command in COMMANDS {
+        outln!("  {:10} {}", command.name, command.summary);
+    }
+    outln!();
+    outln!("LEGACY NAMES:");
+    outln!("  Invoked as 'ape' or 'ape-core' this binary behaves like 'venv activate',");
+    outln!("  and as 'spe' or 'spe-core' like 'venv select', accepting their old flags.");
+    outln!("  'venv ape ...' and 'venv spe ...' do the same.");
+    outln!();
+    outln!("EXAMPLES:");
+    outln!("  venv activate myenv    Activate environment named 'myenv'");
+    outln!("  venv list --scan       Rescan, then list every environment");
+    outln!("  venv doctor --days 30  Audit environments, flagging ones unused for 30 days");
+    outln!("  venv cache status      Show when the cache was last scanned");
+    outln!("  venv watch             Keep the cache current until Ctrl+C");
+    outln!("  venv init zsh          Print the zsh wrapper functions");
+    outln!("  venv help activate     Show help for 'activate'");
+    outln!();
+    show_exit_codes();
+    outln!("FILES:");
+    outln!("  Cache:  {}", display_path(get_cache_path()));
+    outln!("  Config: {}", display_path(get_config_path()));
+    outln!();
+}
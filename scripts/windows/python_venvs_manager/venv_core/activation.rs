@@ -0,0 +1,348 @@
+//! Handing an environment to the shell wrapper that activates it
+//!
+//! CMD and PowerShell wrappers create an empty per-invocation file named in
+//! `APE_HANDOFF` and read the venv path back from it, followed on a second
+//! line by the conda installation for conda environments. bash, zsh, fish
+//! and Nushell wrappers pass `--shell` and evaluate what is printed on stdout.
+//!
+//! Conda environments have no activation scripts of their own; the scripts of
+//! the installation they belong to are run with the environment as argument.
+//! Nushell gets the variables conda would set, so it needs no installation.
+
+use crate::Options;
+use crate::conda;
+use crate::environment::{Environment, Layout};
+use crate::outln;
+use crate::output::{print_debug, print_info, print_success};
+use crate::pyvenv::PyvenvConfig;
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Names the per-invocation file the CMD/PowerShell wrappers read the venv path from
+pub const HANDOFF_ENV: &str = "APE_HANDOFF";
+
+/// Shells that can eval activation code printed on stdout (`--shell`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    Nu,
+}
+
+impl Shell {
+    pub fn from_name(name: &str) -> Option<Shell> {
+        match name.to_ascii_lowercase().as_str() {
+            "bash" => Some(Shell::Bash),
+            "zsh" => Some(Shell::Zsh),
+            "fish" => Some(Shell::Fish),
+            "nu" | "nushell" => Some(Shell::Nu),
+            _ => None,
+        }
+    }
+
+    /// Wrapper functions printed by `--init`
+    pub fn init_script(self) -> &'static str {
+        match self {
+            Shell::Bash | Shell::Zsh => include_str!("../python_venv.sh"),
+            Shell::Fish => include_str!("../python_venv.fish"),
+            Shell::Nu => include_str!("../python_venv.nu"),
+        }
+    }
+
+    /// Script sourced by this shell (Nushell has none, it only needs the bin dir)
+    pub fn activate_script(self, env: &Environment) -> PathBuf {
+        let bin = env.path.join(env.layout.bin_dir());
+        match self {
+            Shell::Bash | Shell::Zsh => bin.join("activate"),
+            Shell::Fish => bin.join("activate.fish"),
+            Shell::Nu => bin,
+        }
+    }
+
+    /// Code the wrapper function evaluates to activate `env` with `script`
+    /// (see [`ActivationPlan::script`])
+    pub fn activation_code(self, env: &Environment, script: &Path) -> String {
+        let conda = env.layout == Layout::Conda;
+        match self {
+            Shell::Bash | Shell::Zsh if conda => {
+                format!("source {} {}", posix_quote(script), posix_quote(&env.path))
+            }
+            Shell::Bash | Shell::Zsh => format!("source {}", posix_quote(script)),
+            Shell::Fish if conda => format!(
+                "{} shell.fish activate {} | source",
+                fish_quote(script),
+                fish_quote(&env.path)
+            ),
+            Shell::Fish => format!("source {}", fish_quote(script)),
+            Shell::Nu if conda => {
+                let mut path = conda::path_dirs(&env.path);
+                if let Some(current) = env::var_os("PATH") {
+                    path.extend(env::split_paths(&current));
+                }
+                serde_json::json!({
+                    "CONDA_PREFIX": env.path,
+                    "CONDA_DEFAULT_ENV": env.name,
+                    "CONDA_PROMPT_MODIFIER": format!("({}) ", env.name),
+                    "PATH": path,
+                })
+                .to_string()
+            }
+            Shell::Nu => {
+                let mut path = vec![script.to_path_buf()];
+                if let Some(current) = env::var_os("PATH") {
+                    path.extend(env::split_paths(&current));
+                }
+                serde_json::json!({
+                    "VIRTUAL_ENV": env.path,
+                    "VIRTUAL_ENV_PROMPT": env.name,
+                    "PATH": path,
+                })
+                .to_string()
+            }
+        }
+    }
+}
+
+fn posix_quote(path: &Path) -> String {
+    format!("'{}'", path.to_string_lossy().replace('\'', "'\\''"))
+}
+
+fn fish_quote(path: &Path) -> String {
+    let escaped = path
+        .to_string_lossy()
+        .replace('\\', "\\\\")
+        .replace('\'', "\\'");
+    format!("'{}'", escaped)
+}
+
+/// How an environment will be activated in the calling shell
+#[derive(Debug)]
+pub struct ActivationPlan<'a> {
+    pub env: &'a Environment,
+    /// `None` for the CMD/PowerShell wrappers reading `APE_HANDOFF`
+    pub shell: Option<Shell>,
+    /// Script the shell will run (for Nushell, the bin directory; for conda
+    /// under fish, the `conda` executable)
+    pub script: PathBuf,
+    /// Installation activating a conda environment through CMD/PowerShell
+    pub conda_root: Option<PathBuf>,
+}
+
+impl<'a> ActivationPlan<'a> {
+    /// Checks that `env` can be activated by the given kind of wrapper
+    pub fn new(env: &'a Environment, shell: Option<Shell>) -> io::Result<Self> {
+        if shell.is_none() && cfg!(windows) && env.layout == Layout::Posix {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!(
+                    "\"{}\" uses a POSIX layout (bin/activate) and cannot be activated from CMD or PowerShell",
+                    env.name
+                ),
+            ));
+        }
+
+        let mut conda_root = None;
+        let script = match shell {
+            Some(Shell::Nu) => Shell::Nu.activate_script(env),
+            _ if env.layout == Layout::Conda => {
+                let root = conda::installation_for(&env.path).ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::NotFound,
+                        format!(
+                            "No conda installation found to activate \"{}\" (micromamba and pixi environments are activated with their own tool)",
+                            env.name
+                        ),
+                    )
+                })?;
+                let script = match shell {
+                    Some(Shell::Fish) => root.join("bin").join("conda"),
+                    None if cfg!(windows) => root.join("Scripts").join("activate.bat"),
+                    _ => root.join("bin").join("activate"),
+                };
+                if shell.is_none() {
+                    conda_root = Some(root);
+                }
+                script
+            }
+            Some(shell) => shell.activate_script(env),
+            None => env.activate_script(),
+        };
+        if !script.exists() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("Activation script not found at \"{}\"", script.display()),
+            ));
+        }
+
+        Ok(ActivationPlan {
+            env,
+            shell,
+            script,
+            conda_root,
+        })
+    }
+
+    /// Prints the activation code on stdout, or writes the handoff file.
+    /// Returns the handoff file that was written, if any.
+    pub fn apply(&self) -> io::Result<Option<PathBuf>> {
+        match self.shell {
+            Some(shell) => {
+                // The only thing ever written to stdout in --shell mode
+                println!("{}", shell.activation_code(self.env, &self.script));
+                Ok(None)
+            }
+            None => write_handoff(&self.env.path, self.conda_root.as_deref())
+                .map(Some)
+                .map_err(|e| {
+                    io::Error::new(
+                        e.kind(),
+                        format!("Failed to hand off activation path: {}", e),
+                    )
+                }),
+        }
+    }
+}
+
+/// Activates `env` through the calling wrapper, reporting progress
+pub fn activate_environment(
+    env: &Environment,
+    shell: Option<Shell>,
+    opts: &Options,
+) -> io::Result<()> {
+    let plan = ActivationPlan::new(env, shell)?;
+
+    if opts.verbose {
+        print_debug(
+            &format!("Activating: {}", env.path.display()),
+            opts.no_color,
+        );
+        print_debug(&format!("Type: {}", env.env_type), opts.no_color);
+        if let Some(project) = &env.project {
+            print_debug(&format!("Project: {}", project.display()), opts.no_color);
+        }
+        if let Some(cfg) = &env.pyvenv {
+            print_pyvenv_details(cfg, opts);
+        }
+        print_debug(
+            &format!("Activation script: {}", plan.script.display()),
+            opts.no_color,
+        );
+        outln!();
+    }
+
+    // Only once the handoff succeeded, so a failure is not preceded by a
+    // success message
+    let handoff_file = plan.apply()?;
+    print_success(
+        &format!("Activating \"{}\" ({})...", env.name, env.env_type),
+        opts.no_color,
+    );
+    outln!();
+
+    if let Some(handoff_file) = handoff_file
+        && opts.verbose
+    {
+        print_debug(
+            &format!("Wrote activation path to: {}", handoff_file.display()),
+            opts.no_color,
+        );
+    }
+
+    let deactivate = match env.layout {
+        Layout::Conda => "conda deactivate",
+        _ => "deactivate",
+    };
+    print_info(
+        &format!(
+            "[{} ready - Type '{}' to deactivate the environment]",
+            env.name, deactivate
+        ),
+        opts.no_color,
+    );
+    outln!();
+
+    Ok(())
+}
+
+/// Verbose-mode listing of what `pyvenv.cfg` recorded
+fn print_pyvenv_details(cfg: &PyvenvConfig, opts: &Options) {
+    let mut details = Vec::new();
+    if let Some(python) = cfg.python_label() {
+        details.push(format!("Python: {}", python));
+    }
+    if let Some(home) = &cfg.home {
+        details.push(format!("Base home: {}", home.display()));
+    }
+    if let Some(creator) = &cfg.creator {
+        details.push(format!("Created by: {}", creator));
+    }
+    if let Some(system) = cfg.include_system_site_packages {
+        details.push(format!("System site-packages: {}", system));
+    }
+    if let Some(prompt) = &cfg.prompt {
+        details.push(format!("Prompt: {}", prompt));
+    }
+    for detail in details {
+        print_debug(&detail, opts.no_color);
+    }
+}
+
+/// Writes the venv path, and on a second line the conda installation that
+/// activates it if any, into the per-invocation handoff file the wrapper named
+/// in `APE_HANDOFF`. The wrapper creates that file empty right before running
+/// the core, so a missing, non-empty, relative or non-regular target means it
+/// is not the one the wrapper asked for and nothing is written.
+pub fn write_handoff(venv_path: &Path, conda_root: Option<&Path>) -> io::Result<PathBuf> {
+    let target = env::var_os(HANDOFF_ENV)
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!(
+                    "{} is not set (run through the ape/spe wrapper, or use --shell)",
+                    HANDOFF_ENV
+                ),
+            )
+        })?;
+
+    let refuse = |reason: &str| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("refusing to write to {}: {}", target.display(), reason),
+        )
+    };
+
+    if !target.is_absolute() {
+        return Err(refuse("path is not absolute"));
+    }
+    let link_meta = fs::symlink_metadata(&target)?;
+    if !link_meta.file_type().is_file() {
+        return Err(refuse("not a regular file"));
+    }
+
+    let mut file = fs::OpenOptions::new().write(true).open(&target)?;
+    let meta = file.metadata()?;
+    if meta.len() != 0 {
+        return Err(refuse("file is not empty"));
+    }
+    // The path must still be the file that was checked above
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        if meta.dev() != link_meta.dev() || meta.ino() != link_meta.ino() {
+            return Err(refuse("file was replaced"));
+        }
+    }
+
+    let mut contents = venv_path.to_string_lossy().to_string();
+    if let Some(root) = conda_root {
+        contents.push('\n');
+        contents.push_str(&root.to_string_lossy());
+    }
+    file.write_all(contents.as_bytes())?;
+    Ok(target)
+}
@@ -0,0 +1,640 @@
+//! Persistent JSON cache of discovered environments
+//!
+//! The file holds a header recording the format version, the tool version,
+//! when and with which settings it was made, followed by the environments.
+//! Caches of older formats are migrated when read, and a cache made with
+//! other search settings is rebuilt by [`refresh_stale_cache`], which also
+//! tells when a scan is older than the configured maximum age.
+//!
+//! Files are replaced whole by renaming a finished temporary file over them,
+//! so a reader always sees a complete snapshot, even during a scan. Writers
+//! take the lock file next to the cache first ([`lock_cache`]), so parallel
+//! scans run one after the other instead of overwriting each other.
+
+use crate::Options;
+use crate::config::{cache_max_age, config_hash, env_path, get_scan_roots, home_dir, no_home};
+use crate::discovery::{dedup_environments, scan_all_venvs};
+use crate::environment::{Environment, unix_now};
+use crate::error::{Error, Result};
+use crate::index::{index_path, load_index, save_index};
+use crate::outln;
+use crate::output::{print_debug, print_info, print_success};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::collections::HashSet;
+use std::env;
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+pub const CACHE_FILENAME: &str = "python_venv_cache.json";
+/// Subdirectory of the user cache dir holding the cache file
+pub const APP_DIR_NAME: &str = "python-venv-tools";
+
+/// Format of the cache files [`save_cache`] writes. Format 1, written before
+/// the header existed, was a bare array of environments.
+pub const CACHE_VERSION: u64 = 2;
+
+/// How often replacing a file a reader has open is retried (Windows refuses
+/// until it is closed), 10 ms apart
+const REPLACE_ATTEMPTS: u32 = 50;
+
+/// What the cache file records about itself
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheHeader {
+    /// Format of the file, see [`CACHE_VERSION`]
+    pub version: u64,
+    /// Version of the tool that made it, empty for migrated caches
+    pub tool_version: String,
+    /// When the scan it holds was made, in seconds since the Unix epoch
+    pub created: u64,
+    /// What that scan walked
+    pub scan_roots: Vec<PathBuf>,
+    /// [`config_hash`] of the settings it was made with
+    pub config_hash: String,
+    /// How long that scan took, in milliseconds (0 if unknown)
+    #[serde(default)]
+    pub scan_ms: u64,
+}
+
+/// Contents of the cache file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheFile {
+    #[serde(flatten)]
+    pub header: CacheHeader,
+    pub environments: Vec<Environment>,
+}
+
+impl CacheFile {
+    /// `environments`, as just found with the current settings
+    pub fn new(environments: Vec<Environment>) -> Result<CacheFile> {
+        Ok(CacheFile {
+            header: CacheHeader {
+                version: CACHE_VERSION,
+                tool_version: env!("CARGO_PKG_VERSION").to_string(),
+                created: unix_now(),
+                scan_roots: get_scan_roots()?.into_iter().map(|r| r.path).collect(),
+                config_hash: config_hash()?,
+                scan_ms: 0,
+            },
+            environments,
+        })
+    }
+
+    /// Seconds since the scan it holds was made
+    pub fn age(&self) -> u64 {
+        unix_now().saturating_sub(self.header.created)
+    }
+
+    /// Whether the search settings changed since the cache was made
+    pub fn is_stale(&self) -> Result<bool> {
+        Ok(self.header.config_hash != config_hash()?)
+    }
+}
+
+/// Cache file: `APE_CACHE`, else `python-venv-tools` under the user cache dir
+pub fn get_cache_path() -> Result<PathBuf> {
+    if let Some(path) = env_path("APE_CACHE") {
+        return Ok(path);
+    }
+    Ok(user_cache_dir()?.join(APP_DIR_NAME).join(CACHE_FILENAME))
+}
+
+/// `XDG_CACHE_HOME`, else `%LOCALAPPDATA%` on Windows, else `~/.cache`
+pub fn user_cache_dir() -> Result<PathBuf> {
+    if let Some(dir) = env_path("XDG_CACHE_HOME").filter(|p| p.is_absolute()) {
+        return Ok(dir);
+    }
+    if cfg!(windows)
+        && let Some(dir) = env_path("LOCALAPPDATA")
+    {
+        return Ok(dir);
+    }
+    Ok(home_dir()
+        .ok_or_else(|| no_home("cache", "APE_CACHE"))?
+        .join(".cache"))
+}
+
+/// Moves a cache left in the temp dir by older versions to `cache_file`,
+/// unless a cache already exists there
+pub fn migrate_legacy_cache(cache_file: &Path, opts: &Options) {
+    let legacy = env::temp_dir().join(CACHE_FILENAME);
+    if cache_file.exists() || !legacy.is_file() || legacy == cache_file {
+        return;
+    }
+
+    if let Some(parent) = cache_file.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    // rename fails across volumes (e.g. tmpfs /tmp), so fall back to copying
+    let moved = fs::rename(&legacy, cache_file).is_ok()
+        || (fs::copy(&legacy, cache_file).is_ok() && fs::remove_file(&legacy).is_ok());
+
+    if opts.verbose {
+        if moved {
+            print_debug(
+                &format!(
+                    "Migrated cache from {} to {}",
+                    legacy.display(),
+                    cache_file.display()
+                ),
+                opts.no_color,
+            );
+        } else {
+            print_debug(
+                &format!("Could not migrate cache from {}", legacy.display()),
+                opts.no_color,
+            );
+        }
+    }
+}
+
+/// The cached environments, each listed once (caches of older versions may
+/// hold duplicates), sorted by name
+pub fn load_cache(cache_file: &Path, opts: &Options) -> Result<Vec<Environment>> {
+    read_cache(cache_file, opts).map(|cache| cache.environments)
+}
+
+/// The cache file, migrated to the current format. Entries this version
+/// cannot read are dropped rather than failing the whole cache.
+pub fn read_cache(cache_file: &Path, opts: &Options) -> Result<CacheFile> {
+    if opts.verbose {
+        print_debug("Loading cache...", opts.no_color);
+    }
+
+    let contents = fs::read_to_string(cache_file).map_err(|e| Error::cache(cache_file, e))?;
+    let value: Value = serde_json::from_str(&contents).map_err(|e| Error::cache(cache_file, e))?;
+    let mut value = migrate(value, cache_file, opts)?;
+
+    let entries = match value.get_mut("environments").map(Value::take) {
+        Some(Value::Array(entries)) => entries,
+        _ => return Err(Error::cache(cache_file, "no environments list")),
+    };
+    let header: CacheHeader =
+        serde_json::from_value(value).map_err(|e| Error::cache(cache_file, e))?;
+    let total = entries.len();
+    let environments: Vec<Environment> = entries
+        .into_iter()
+        .filter_map(|entry| serde_json::from_value(entry).ok())
+        .collect();
+    if opts.verbose && environments.len() < total {
+        print_debug(
+            &format!(
+                "Skipped {} unreadable cache entries",
+                total - environments.len()
+            ),
+            opts.no_color,
+        );
+    }
+    let environments = dedup_environments(environments, opts);
+
+    if opts.verbose {
+        print_debug(
+            &format!("Loaded {} environments from cache", environments.len()),
+            opts.no_color,
+        );
+    }
+
+    Ok(CacheFile {
+        header,
+        environments,
+    })
+}
+
+/// Upgrades the contents of a cache file written in an older format to the
+/// current one, a format at a time
+fn migrate(mut value: Value, cache_file: &Path, opts: &Options) -> Result<Value> {
+    loop {
+        let version = match &value {
+            Value::Array(_) => 1,
+            _ => value
+                .get("version")
+                .and_then(Value::as_u64)
+                .ok_or_else(|| Error::cache(cache_file, "no format version"))?,
+        };
+        if version == CACHE_VERSION {
+            return Ok(value);
+        }
+        if version > CACHE_VERSION {
+            return Err(Error::cache(
+                cache_file,
+                format!(
+                    "format {} is newer than this version reads ({}); run 'venv scan' to rebuild it",
+                    version, CACHE_VERSION
+                ),
+            ));
+        }
+
+        if opts.verbose {
+            print_debug(
+                &format!("Migrating cache from format {}", version),
+                opts.no_color,
+            );
+        }
+        value = match version {
+            // Taken to match the current settings: it predates recording them
+            1 => json!({
+                "version": 2,
+                "tool_version": "",
+                "created": modified_secs(cache_file),
+                "scan_roots": get_scan_roots()?
+                    .into_iter()
+                    .map(|r| r.path)
+                    .collect::<Vec<_>>(),
+                "config_hash": config_hash()?,
+                "environments": value,
+            }),
+            _ => {
+                return Err(Error::cache(
+                    cache_file,
+                    format!("unknown format {}", version),
+                ));
+            }
+        };
+    }
+}
+
+/// Modification time of `path` in seconds since the Unix epoch, 0 if unknown
+fn modified_secs(path: &Path) -> u64 {
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_secs())
+}
+
+pub fn save_cache(cache_file: &Path, cache: &CacheFile, opts: &Options) -> Result<()> {
+    if opts.verbose {
+        print_debug(
+            &format!("Saving cache to {}", cache_file.display()),
+            opts.no_color,
+        );
+        print_debug(
+            &format!(
+                "Number of environments to save: {}",
+                cache.environments.len()
+            ),
+            opts.no_color,
+        );
+    }
+
+    let json = serde_json::to_string_pretty(cache).map_err(|e| Error::cache(cache_file, e))?;
+
+    if let Some(parent) = cache_file.parent() {
+        fs::create_dir_all(parent).map_err(|e| Error::cache(cache_file, e))?;
+    }
+    write_atomic(cache_file, &json).map_err(|e| Error::cache(cache_file, e))?;
+
+    if opts.verbose {
+        print_debug("Cache saved successfully", opts.no_color);
+    }
+
+    Ok(())
+}
+
+/// `--scan`: scans the scan roots (the whole home folder by default) and
+/// replaces the cache with the result. Only directories changed since the
+/// last scan are read, unless `full` is set or there is no index of that
+/// scan.
+pub fn rescan(cache_file: &Path, full: bool, opts: &Options) -> Result<Vec<Environment>> {
+    let started = Instant::now();
+    // Held until the cache and index are saved; a scan waiting for another
+    // starts from the index that one leaves
+    let _lock = lock_cache(cache_file, opts)?;
+    let index_file = index_path(cache_file);
+    let index = if full {
+        Vec::new()
+    } else {
+        load_index(&index_file)
+    };
+    if !index.is_empty() {
+        print_info("Scanning for changes since the last scan...", opts.no_color);
+    } else {
+        print_info("Performing comprehensive scan...", opts.no_color);
+        print_info("This may take a moment...", opts.no_color);
+    }
+    outln!();
+
+    // Keep when each environment was last used, which a scan cannot see
+    let previous = load_cache(cache_file, &Options::default()).unwrap_or_default();
+    let (mut environments, index) = scan_all_venvs(&index, opts)?;
+    for env in &mut environments {
+        env.last_used = previous
+            .iter()
+            .find(|p| p.path == env.path)
+            .and_then(|p| p.last_used);
+    }
+    print_success(
+        &format!("Found {} environments.", environments.len()),
+        opts.no_color,
+    );
+
+    let mut cache = CacheFile::new(environments)?;
+    cache.header.scan_ms = u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX);
+    save_cache(cache_file, &cache, opts)?;
+    // Without an index the next scan is a full one, which is only slower
+    if let Err(e) = save_index(&index_file, &index)
+        && opts.verbose
+    {
+        print_debug(&format!("Could not save scan index: {}", e), opts.no_color);
+    }
+    if opts.verbose {
+        print_debug(
+            &format!("Scan took {:.2?}", started.elapsed()),
+            opts.no_color,
+        );
+    }
+    print_success("Cache updated.", opts.no_color);
+    outln!();
+
+    Ok(cache.environments)
+}
+
+/// Rescans if the cache was made with other directories, scan roots or
+/// exclude rules than the config file now sets. Returns whether the cache is
+/// older than [`cache_max_age`] instead: it is still served, and the caller
+/// decides how to refresh it. A missing or unreadable cache is left to the
+/// caller.
+pub fn refresh_stale_cache(cache_file: &Path, opts: &Options) -> Result<bool> {
+    if !cache_file.exists() {
+        return Ok(false);
+    }
+    let Ok(cache) = read_cache(cache_file, &Options::default()) else {
+        return Ok(false);
+    };
+    if cache.is_stale()? {
+        print_info(
+            "Search settings changed since the last scan, rescanning...",
+            opts.no_color,
+        );
+        rescan(cache_file, false, opts)?;
+        return Ok(false);
+    }
+
+    let expired = cache_max_age()?.is_some_and(|max_age| cache.age() > max_age);
+    if expired && opts.verbose {
+        print_debug(
+            &format!("Cache is {} hours old", cache.age() / 3600),
+            opts.no_color,
+        );
+    }
+    Ok(expired)
+}
+
+/// What `venv cache status` reports
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheStatus {
+    pub path: PathBuf,
+    #[serde(flatten)]
+    pub header: CacheHeader,
+    /// Seconds since the scan
+    pub age: u64,
+    /// [`cache_max_age`] in effect
+    pub max_age: Option<u64>,
+    pub environments: usize,
+    /// Whether the search settings changed since the scan
+    pub stale: bool,
+    /// Whether a scan holds the lock right now
+    pub scanning: bool,
+}
+
+/// Describes the cache at `cache_file`
+pub fn cache_status(cache_file: &Path) -> Result<CacheStatus> {
+    let cache = read_cache(cache_file, &Options::default())?;
+    Ok(CacheStatus {
+        path: cache_file.to_path_buf(),
+        age: cache.age(),
+        max_age: cache_max_age()?,
+        environments: cache.environments.len(),
+        stale: cache.is_stale()?,
+        scanning: matches!(try_lock_cache(cache_file), Ok(None)),
+        header: cache.header,
+    })
+}
+
+/// Records that `env` was just activated, adding it to the cache if it is
+/// not listed yet. Does nothing without a cache, since a cache holding only
+/// this environment would hide all others from `spe`, nor while a scan is
+/// replacing the cache, rather than keep the activation waiting.
+pub fn record_use(cache_file: &Path, env: &Environment, opts: &Options) {
+    if !cache_file.exists() {
+        return;
+    }
+    let _lock = match try_lock_cache(cache_file) {
+        Ok(Some(lock)) => lock,
+        Ok(None) => {
+            if opts.verbose {
+                print_debug(
+                    "Not recording use: a scan is updating the cache",
+                    opts.no_color,
+                );
+            }
+            return;
+        }
+        Err(e) => {
+            if opts.verbose {
+                print_debug(&format!("Could not record use: {}", e), opts.no_color);
+            }
+            return;
+        }
+    };
+    let quiet = Options::default();
+    let result = read_cache(cache_file, &quiet).and_then(|mut cache| {
+        let now = Some(unix_now());
+        match cache.environments.iter_mut().find(|e| e.path == env.path) {
+            Some(cached) => cached.last_used = now,
+            None => cache.environments.push(Environment {
+                last_used: now,
+                ..env.clone()
+            }),
+        }
+        save_cache(cache_file, &cache, &quiet)
+    });
+    if opts.verbose
+        && let Err(e) = result
+    {
+        print_debug(&format!("Could not record use: {}", e), opts.no_color);
+    }
+}
+
+/// Brings the cache in line with what a lookup saw on disk (see
+/// [`merge_into_cache`]). Like [`record_use`], does nothing without a cache
+/// or while a scan holds it; a failure only costs the update.
+pub fn update_cache(cache_file: &Path, gone: &[PathBuf], found: &[Environment], opts: &Options) {
+    if (gone.is_empty() && found.is_empty()) || !cache_file.exists() {
+        return;
+    }
+    let Ok(Some(_lock)) = try_lock_cache(cache_file) else {
+        if opts.verbose {
+            print_debug("Cache busy, not updating it", opts.no_color);
+        }
+        return;
+    };
+
+    match merge_into_cache(cache_file, gone, found) {
+        Ok((removed, added)) => {
+            if opts.verbose && !(removed.is_empty() && added.is_empty()) {
+                print_debug(
+                    &format!(
+                        "Updated cache: removed {} vanished, added {} found environments",
+                        removed.len(),
+                        added.len()
+                    ),
+                    opts.no_color,
+                );
+            }
+        }
+        Err(e) => {
+            if opts.verbose {
+                print_debug(&format!("Could not update cache: {}", e), opts.no_color);
+            }
+        }
+    }
+}
+
+/// Drops the cached environments at or below a path in `gone` that no
+/// longer exist, and adds those in `found` that are not cached yet, saving
+/// the cache if that changed it. The caller holds the cache lock. Returns
+/// the environments removed and those added.
+pub fn merge_into_cache(
+    cache_file: &Path,
+    gone: &[PathBuf],
+    found: &[Environment],
+) -> Result<(Vec<Environment>, Vec<Environment>)> {
+    let quiet = Options::default();
+    let mut cache = read_cache(cache_file, &quiet)?;
+    let (removed, mut kept): (Vec<Environment>, Vec<Environment>) = cache
+        .environments
+        .into_iter()
+        .partition(|env| gone.iter().any(|path| env.path.starts_with(path)) && !env.exists());
+    let known: HashSet<PathBuf> = kept.iter().map(|env| env.path.clone()).collect();
+    kept.extend(found.iter().cloned());
+    // Also merges environments found through another path
+    cache.environments = dedup_environments(kept, &quiet);
+    let added: Vec<Environment> = cache
+        .environments
+        .iter()
+        .filter(|env| !known.contains(&env.path))
+        .cloned()
+        .collect();
+
+    if !removed.is_empty() || !added.is_empty() {
+        save_cache(cache_file, &cache, &quiet)?;
+    }
+    Ok((removed, added))
+}
+
+/// Lock file next to `cache_file` that writers hold
+pub fn lock_path(cache_file: &Path) -> PathBuf {
+    cache_file.with_extension("lock")
+}
+
+/// Exclusive hold on the cache, released when dropped
+#[derive(Debug)]
+pub struct CacheLock {
+    _file: File,
+}
+
+/// Takes the cache lock, waiting while another process or thread holds it
+pub fn lock_cache(cache_file: &Path, opts: &Options) -> Result<CacheLock> {
+    let (file, path) = open_lock(cache_file)?;
+    match file.try_lock() {
+        Ok(()) => {}
+        Err(TryLockError::WouldBlock) => {
+            print_info("Waiting for another scan to finish...", opts.no_color);
+            file.lock().map_err(|e| Error::cache(&path, e))?;
+        }
+        Err(TryLockError::Error(e)) => return Err(Error::cache(&path, e)),
+    }
+    Ok(CacheLock { _file: file })
+}
+
+/// Takes the cache lock, or returns `None` if someone else holds it
+pub fn try_lock_cache(cache_file: &Path) -> Result<Option<CacheLock>> {
+    let (file, path) = open_lock(cache_file)?;
+    match file.try_lock() {
+        Ok(()) => Ok(Some(CacheLock { _file: file })),
+        Err(TryLockError::WouldBlock) => Ok(None),
+        Err(TryLockError::Error(e)) => Err(Error::cache(&path, e)),
+    }
+}
+
+fn open_lock(cache_file: &Path) -> Result<(File, PathBuf)> {
+    let path = lock_path(cache_file);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| Error::cache(&path, e))?;
+    }
+    let file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(false)
+        .open(&path)
+        .map_err(|e| Error::cache(&path, e))?;
+    Ok((file, path))
+}
+
+/// Writes `contents` to a temporary file next to `path`, then renames it
+/// over `path`, so readers see the old file or the new one, never part of
+/// one
+pub(crate) fn write_atomic(path: &Path, contents: &str) -> io::Result<()> {
+    // Unique per process and per write, for writers in other threads
+    static WRITES: AtomicUsize = AtomicUsize::new(0);
+    let temp = path.with_file_name(format!(
+        ".{}.{}-{}.tmp",
+        path.file_name().unwrap_or_default().to_string_lossy(),
+        process::id(),
+        WRITES.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    let result = write_synced(&temp, contents).and_then(|()| replace(&temp, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+fn write_synced(path: &Path, contents: &str) -> io::Result<()> {
+    let mut file = File::create(path)?;
+    file.write_all(contents.as_bytes())?;
+    file.sync_all()
+}
+
+fn replace(from: &Path, to: &Path) -> io::Result<()> {
+    let mut attempts = 1;
+    loop {
+        match fs::rename(from, to) {
+            Err(e)
+                if e.kind() == io::ErrorKind::PermissionDenied && attempts < REPLACE_ATTEMPTS =>
+            {
+                attempts += 1;
+                thread::sleep(Duration::from_millis(10));
+            }
+            result => return result,
+        }
+    }
+}
+
+/// `--clean`: removes the cache file, and the index of the last scan
+pub fn clean_cache(cache_file: &Path, opts: &Options) -> Result<()> {
+    print_info("Removing cache file...", opts.no_color);
+    let _ = fs::remove_file(index_path(cache_file));
+    if cache_file.exists() {
+        fs::remove_file(cache_file)
+            .map_err(|e| Error::cache(cache_file, format!("failed to remove: {}", e)))?;
+        print_success(
+            &format!("Cache file removed successfully: {}", cache_file.display()),
+            opts.no_color,
+        );
+    } else {
+        print_info(
+            &format!("Cache file does not exist: {}", cache_file.display()),
+            opts.no_color,
+        );
+    }
+    outln!();
+    Ok(())
+}
@@ -0,0 +1,244 @@
+//! Conda, mamba and micromamba environments
+//!
+//! Conda environments have no `pyvenv.cfg`, so rather than walking the disk
+//! they are enumerated from the places conda itself records them:
+//! `~/.conda/environments.txt`, the `envs_dirs` of every `.condarc`, and the
+//! `envs` folder of each installation, whose root is the `base` environment.
+
+use crate::config::{env_path, home_dir};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Folder names installers give a conda, mamba or micromamba root in the
+/// home folder
+const INSTALL_DIR_NAMES: &[&str] = &[
+    "miniconda3",
+    "anaconda3",
+    "miniforge3",
+    "mambaforge",
+    "micromamba",
+    "miniconda",
+    "anaconda",
+];
+
+/// Whether `path` is a conda environment (it has a `conda-meta` folder)
+pub fn is_conda_env(path: &Path) -> bool {
+    path.join("conda-meta").is_dir()
+}
+
+/// Whether `path` is the root of an installation that can activate
+/// environments (ships conda's activation scripts)
+pub fn is_installation(path: &Path) -> bool {
+    if cfg!(windows) {
+        path.join("Scripts").join("activate.bat").is_file()
+    } else {
+        path.join("bin").join("activate").is_file() && path.join("bin").join("conda").is_file()
+    }
+}
+
+/// Whether the conda environment at `path` is the root (`base`) environment
+/// of an installation
+pub fn is_base(path: &Path) -> bool {
+    is_installation(path) || path.join("envs").is_dir()
+}
+
+/// Installation roots: `CONDA_EXE`'s, `MAMBA_ROOT_PREFIX`, the usual
+/// install folders in the home folder (and `%ProgramData%` on Windows), and
+/// `~/.local/share/mamba`. Only existing directories are returned.
+pub fn installation_roots() -> Vec<PathBuf> {
+    let mut roots = Vec::new();
+    // <root>/bin/conda or <root>\Scripts\conda.exe
+    if let Some(exe) = env_path("CONDA_EXE")
+        && let Some(root) = exe.parent().and_then(Path::parent)
+    {
+        roots.push(root.to_path_buf());
+    }
+    roots.extend(env_path("MAMBA_ROOT_PREFIX"));
+    if let Some(home) = home_dir() {
+        roots.extend(INSTALL_DIR_NAMES.iter().map(|name| home.join(name)));
+        roots.push(home.join(".local").join("share").join("mamba"));
+    }
+    if cfg!(windows)
+        && let Some(program_data) = env_path("ProgramData")
+    {
+        roots.extend(INSTALL_DIR_NAMES.iter().map(|name| program_data.join(name)));
+    }
+    roots.retain(|root| root.is_dir());
+    dedup(roots)
+}
+
+/// Every conda environment that can be found without walking the disk,
+/// base environments included
+pub fn environment_paths() -> Vec<PathBuf> {
+    let roots = installation_roots();
+    let mut paths: Vec<PathBuf> = roots.clone();
+    paths.extend(environments_txt());
+
+    let mut envs_dirs: Vec<PathBuf> = roots.iter().map(|root| root.join("envs")).collect();
+    if let Some(home) = home_dir() {
+        envs_dirs.push(home.join(".conda").join("envs"));
+    }
+    for var in ["CONDA_ENVS_PATH", "CONDA_ENVS_DIRS"] {
+        if let Some(value) = env::var_os(var) {
+            envs_dirs.extend(env::split_paths(&value).filter(|p| !p.as_os_str().is_empty()));
+        }
+    }
+    for condarc in condarc_files(&roots) {
+        if let Ok(contents) = fs::read_to_string(&condarc) {
+            envs_dirs.extend(parse_envs_dirs(&contents).iter().map(|d| expand_home(d)));
+        }
+    }
+
+    for dir in dedup(envs_dirs) {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        paths.extend(entries.filter_map(|e| e.ok()).map(|e| e.path()));
+    }
+
+    paths.retain(|path| is_conda_env(path));
+    dedup(paths)
+}
+
+/// Environments listed in `~/.conda/environments.txt`, which conda updates
+/// whenever it creates or removes one
+fn environments_txt() -> Vec<PathBuf> {
+    let Some(home) = home_dir() else {
+        return Vec::new();
+    };
+    fs::read_to_string(home.join(".conda").join("environments.txt"))
+        .map(|contents| {
+            contents
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(PathBuf::from)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// `.condarc`/`.mambarc` files conda and mamba read, user ones first
+fn condarc_files(roots: &[PathBuf]) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = Vec::new();
+    files.extend(env_path("CONDARC"));
+    files.extend(env_path("MAMBARC"));
+    if let Some(config) = env_path("XDG_CONFIG_HOME")
+        .filter(|p| p.is_absolute())
+        .or_else(|| home_dir().map(|home| home.join(".config")))
+    {
+        files.push(config.join("conda").join(".condarc"));
+        files.push(config.join("conda").join("condarc"));
+    }
+    if let Some(home) = home_dir() {
+        files.push(home.join(".condarc"));
+        files.push(home.join(".conda").join(".condarc"));
+        files.push(home.join(".conda").join("condarc"));
+        files.push(home.join(".mambarc"));
+    }
+    files.extend(roots.iter().map(|root| root.join(".condarc")));
+    files.retain(|file| file.is_file());
+    files
+}
+
+/// The `envs_dirs` (or older `envs_path`) list of a `.condarc`, in either
+/// block (`- path` lines) or flow (`[a, b]`) style
+pub fn parse_envs_dirs(contents: &str) -> Vec<String> {
+    let mut dirs = Vec::new();
+    let mut in_list = false;
+
+    for line in contents.lines() {
+        let content = line.split(" #").next().unwrap_or("").trim_end();
+        if content.trim().is_empty() || content.trim_start().starts_with('#') {
+            continue;
+        }
+        let indented = content.starts_with([' ', '\t']);
+        if !indented {
+            in_list = false;
+            let Some((key, value)) = content.split_once(':') else {
+                continue;
+            };
+            if !matches!(key.trim(), "envs_dirs" | "envs_path") {
+                continue;
+            }
+            let value = value.trim();
+            if let Some(flow) = value.strip_prefix('[') {
+                dirs.extend(
+                    flow.trim_end_matches(']')
+                        .split(',')
+                        .map(unquote)
+                        .filter(|d| !d.is_empty()),
+                );
+            } else {
+                in_list = value.is_empty();
+            }
+        } else if in_list && let Some(item) = content.trim_start().strip_prefix('-') {
+            let item = unquote(item);
+            if !item.is_empty() {
+                dirs.push(item);
+            }
+        }
+    }
+
+    dirs
+}
+
+fn unquote(value: &str) -> String {
+    value.trim().trim_matches(['\'', '"']).to_string()
+}
+
+/// `~/envs` -> `<home>/envs`
+fn expand_home(path: &str) -> PathBuf {
+    match path.strip_prefix("~/").or_else(|| path.strip_prefix("~\\")) {
+        Some(rest) => home_dir().unwrap_or_default().join(rest),
+        None => PathBuf::from(path),
+    }
+}
+
+/// Installation whose activation scripts activate the environment at
+/// `env_path`: the environment itself when it is a base, the installation
+/// whose `envs` folder holds it, else the first installation found
+pub fn installation_for(env_path: &Path) -> Option<PathBuf> {
+    if is_installation(env_path) {
+        return Some(env_path.to_path_buf());
+    }
+    if let Some(envs) = env_path.parent()
+        && envs.file_name().is_some_and(|n| n == "envs")
+        && let Some(root) = envs.parent()
+        && is_installation(root)
+    {
+        return Some(root.to_path_buf());
+    }
+    installation_roots()
+        .into_iter()
+        .chain(environments_txt())
+        .find(|root| is_installation(root))
+}
+
+/// Directories conda puts on `PATH` when activating the environment at
+/// `prefix`
+pub fn path_dirs(prefix: &Path) -> Vec<PathBuf> {
+    if cfg!(windows) {
+        vec![
+            prefix.to_path_buf(),
+            prefix.join("Library").join("mingw-w64").join("bin"),
+            prefix.join("Library").join("usr").join("bin"),
+            prefix.join("Library").join("bin"),
+            prefix.join("Scripts"),
+            prefix.join("bin"),
+        ]
+    } else {
+        vec![prefix.join("bin")]
+    }
+}
+
+fn dedup(paths: Vec<PathBuf>) -> Vec<PathBuf> {
+    let mut unique: Vec<PathBuf> = Vec::new();
+    for path in paths {
+        if !unique.contains(&path) {
+            unique.push(path);
+        }
+    }
+    unique
+}
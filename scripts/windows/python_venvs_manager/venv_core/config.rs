@@ -0,0 +1,353 @@
+//! `python_venv_config.toml` and the directories searched for environments
+
+use crate::Options;
+use crate::discovery::MAX_SEARCH_DEPTH;
+use crate::error::{Error, Result};
+use crate::output::print_debug;
+use serde::Deserialize;
+use serde::de::value::MapAccessDeserializer;
+use serde::de::{self, Deserializer, MapAccess, Visitor};
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+pub const CONFIG_FILENAME: &str = "python_venv_config.toml";
+
+/// Hours after which a scan is refreshed in the background, unless the
+/// config file sets `cache_max_age_hours`
+pub const DEFAULT_CACHE_MAX_AGE_HOURS: u64 = 24;
+
+/// Contents of `python_venv_config.toml`
+#[derive(Debug, Deserialize)]
+pub struct UserConfig {
+    /// Replaces the predefined search directories when non-empty
+    pub directories: Option<Vec<DirectoryEntry>>,
+    /// Replaces the home folder as what `--scan` walks when non-empty
+    pub scan_roots: Option<Vec<DirectoryEntry>>,
+    /// Gitignore-style rules for folders to skip, after the defaults
+    pub exclude: Option<Vec<String>>,
+    /// Also skip what `.gitignore` and `.ignore` files ignore
+    pub respect_gitignore: Option<bool>,
+    /// Age of a scan, in hours, after which it is refreshed; 0 never does
+    pub cache_max_age_hours: Option<u64>,
+}
+
+/// An entry of `directories` or `scan_roots`: a path, or a table with
+/// settings for it
+#[derive(Debug)]
+pub enum DirectoryEntry {
+    Path(String),
+    Table(DirectoryTable),
+}
+
+// Not `#[serde(untagged)]`, which would replace a table's own error (such as
+// a misspelled key) with a generic one located at the start of the list
+impl<'de> Deserialize<'de> for DirectoryEntry {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        struct EntryVisitor;
+
+        impl<'de> Visitor<'de> for EntryVisitor {
+            type Value = DirectoryEntry;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a path, or a table of path, depth, follow_symlinks and recursive")
+            }
+
+            fn visit_str<E: de::Error>(self, path: &str) -> std::result::Result<DirectoryEntry, E> {
+                Ok(DirectoryEntry::Path(path.to_string()))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(
+                self,
+                map: A,
+            ) -> std::result::Result<DirectoryEntry, A::Error> {
+                DirectoryTable::deserialize(MapAccessDeserializer::new(map))
+                    .map(DirectoryEntry::Table)
+            }
+        }
+
+        deserializer.deserialize_any(EntryVisitor)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DirectoryTable {
+    pub path: String,
+    /// Levels below `path` to look at
+    pub depth: Option<usize>,
+    /// Descend into symlinked (or junctioned) folders
+    pub follow_symlinks: Option<bool>,
+    /// `false` looks at `path` and its direct subfolders only
+    pub recursive: Option<bool>,
+}
+
+/// A directory to look for environments in, and how far
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchDir {
+    pub path: PathBuf,
+    /// Levels below `path` to look at; `None` for no limit
+    pub depth: Option<usize>,
+    pub follow_symlinks: bool,
+}
+
+impl SearchDir {
+    /// `path`, searched [`MAX_SEARCH_DEPTH`] levels deep without following
+    /// symlinks
+    pub fn new(path: PathBuf) -> SearchDir {
+        SearchDir {
+            path,
+            depth: Some(MAX_SEARCH_DEPTH),
+            follow_symlinks: false,
+        }
+    }
+
+    /// Whether an environment at `path` is within reach of this directory
+    pub fn reaches(&self, path: &Path) -> bool {
+        path.strip_prefix(&self.path).is_ok_and(|rest| {
+            self.depth
+                .is_none_or(|depth| rest.components().count() <= depth)
+        })
+    }
+
+    fn from_entry(
+        entry: &DirectoryEntry,
+        default_depth: Option<usize>,
+        home: Option<&Path>,
+    ) -> Result<SearchDir> {
+        Ok(match entry {
+            DirectoryEntry::Path(path) => SearchDir {
+                path: expand_path(path, home)?,
+                depth: default_depth,
+                follow_symlinks: false,
+            },
+            DirectoryEntry::Table(table) => SearchDir {
+                path: expand_path(&table.path, home)?,
+                depth: match table.recursive {
+                    Some(false) => Some(table.depth.unwrap_or(1).min(1)),
+                    _ => table.depth.or(default_depth),
+                },
+                follow_symlinks: table.follow_symlinks.unwrap_or(false),
+            },
+        })
+    }
+}
+
+/// Config file: `APE_CONFIG`, else `XDG_CONFIG_HOME`, else `~/.config`
+pub fn get_config_path() -> Result<PathBuf> {
+    if let Some(path) = env_path("APE_CONFIG") {
+        return Ok(path);
+    }
+    let dir = match env_path("XDG_CONFIG_HOME").filter(|p| p.is_absolute()) {
+        Some(dir) => dir,
+        None => home_dir()
+            .ok_or_else(|| no_home("config", "APE_CONFIG"))?
+            .join(".config"),
+    };
+    Ok(dir.join(CONFIG_FILENAME))
+}
+
+/// Error for a `kind` directory that cannot be located without a home
+/// folder, rather than guessing one relative to the working directory
+pub(crate) fn no_home(kind: &'static str, var: &'static str) -> Error {
+    Error::NoHome { kind, var }
+}
+
+/// User home directory: `USERPROFILE` on Windows, `HOME` on Linux/macOS/WSL
+pub fn home_dir() -> Option<PathBuf> {
+    env::var_os("USERPROFILE")
+        .filter(|p| !p.is_empty())
+        .or_else(|| env::var_os("HOME").filter(|p| !p.is_empty()))
+        .map(PathBuf::from)
+}
+
+/// Non-empty environment variable as a path
+pub(crate) fn env_path(var: &str) -> Option<PathBuf> {
+    env::var_os(var)
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+}
+
+/// The config file, read once per run; `None` if it does not exist. The
+/// settings a command uses and the `config_hash` it stores all come from
+/// this one read, so an edit in between cannot mix two versions. `venv
+/// watch` keeps the settings it started with.
+pub fn load_user_config() -> Result<Option<&'static UserConfig>> {
+    static CONFIG: OnceLock<Option<UserConfig>> = OnceLock::new();
+    if let Some(config) = CONFIG.get() {
+        return Ok(config.as_ref());
+    }
+    let config = read_user_config()?;
+    Ok(CONFIG.get_or_init(|| config).as_ref())
+}
+
+/// Reads the config file; `None` if it does not exist
+fn read_user_config() -> Result<Option<UserConfig>> {
+    let config_path = get_config_path()?;
+    if !config_path.exists() {
+        return Ok(None);
+    }
+
+    let contents = fs::read_to_string(&config_path).map_err(|e| Error::Config {
+        path: config_path.clone(),
+        line: None,
+        column: None,
+        message: e.to_string(),
+    })?;
+    parse_user_config(&config_path, &contents).map(Some)
+}
+
+/// Parses config file contents, locating any error by line and column
+pub fn parse_user_config(path: &Path, contents: &str) -> Result<UserConfig> {
+    toml::from_str(contents).map_err(|e: toml::de::Error| {
+        let (line, column) = match e.span() {
+            Some(span) => {
+                let before = &contents[..span.start.min(contents.len())];
+                let line = before.matches('\n').count() + 1;
+                let column = before.chars().rev().take_while(|&c| c != '\n').count() + 1;
+                (Some(line), Some(column))
+            }
+            None => (None, None),
+        };
+        Error::Config {
+            path: path.to_path_buf(),
+            line,
+            column,
+            message: e.message().to_string(),
+        }
+    })
+}
+
+/// `%USERPROFILE%` and a leading `~` replaced by `home`; an error if `path`
+/// has either and there is no home folder
+fn expand_path(path: &str, home: Option<&Path>) -> Result<PathBuf> {
+    if !path.contains("%USERPROFILE%") && !path.starts_with('~') {
+        return Ok(PathBuf::from(path));
+    }
+    let home = home.ok_or_else(|| no_home("home", "USERPROFILE"))?;
+    let expanded = path.replace("%USERPROFILE%", &home.to_string_lossy());
+    Ok(match expanded.strip_prefix('~') {
+        Some(rest) => home.join(rest.trim_start_matches(['/', '\\'])),
+        None => PathBuf::from(expanded),
+    })
+}
+
+/// Directories from the config file, or the predefined ones if none are set
+pub fn get_search_dirs() -> Result<Vec<SearchDir>> {
+    // First, try to load custom directories from config file
+    if let Some(dirs) = load_user_config()?.and_then(|c| c.directories.as_ref()) {
+        let home = home_dir();
+        let custom_dirs: Vec<SearchDir> = dirs
+            .iter()
+            .map(|d| SearchDir::from_entry(d, Some(MAX_SEARCH_DEPTH), home.as_deref()))
+            .collect::<Result<_>>()?;
+
+        if !custom_dirs.is_empty() {
+            return Ok(custom_dirs);
+        }
+    }
+
+    // Fall back to predefined directories
+    Ok(get_predefined_dirs()
+        .into_iter()
+        .map(SearchDir::new)
+        .collect())
+}
+
+pub fn get_predefined_dirs() -> Vec<PathBuf> {
+    let user_profile = match home_dir() {
+        Some(path) => path,
+        None => return Vec::new(),
+    };
+
+    let mut dirs = vec![
+        user_profile.clone(),
+        user_profile.join("code"),
+        user_profile.join("dev"),
+        user_profile.join("projects"),
+    ];
+    if cfg!(windows) {
+        dirs.push(user_profile.join("AppData").join("Local").join("Programs"));
+    }
+    dirs
+}
+
+/// What `--scan` walks: `scan_roots` from the config file, else the whole
+/// home folder. Roots have no depth limit unless one is configured.
+pub fn get_scan_roots() -> Result<Vec<SearchDir>> {
+    let home = home_dir();
+    if let Some(roots) = load_user_config()?.and_then(|c| c.scan_roots.as_ref()) {
+        let roots: Vec<SearchDir> = roots
+            .iter()
+            .map(|r| SearchDir::from_entry(r, None, home.as_deref()))
+            .collect::<Result<_>>()?;
+        if !roots.is_empty() {
+            return Ok(roots);
+        }
+    }
+
+    Ok(home
+        .map(|path| SearchDir {
+            path,
+            depth: None,
+            follow_symlinks: false,
+        })
+        .into_iter()
+        .collect())
+}
+
+/// Verbose-mode listing of the directories that will be searched
+pub fn print_search_dirs(dirs: &[SearchDir], opts: &Options) {
+    if !opts.verbose {
+        return;
+    }
+    print_debug("Verbose mode enabled.", opts.no_color);
+    print_debug("Directories to be scanned:", opts.no_color);
+    for dir in dirs {
+        crate::outln!("  {}", describe(dir));
+    }
+    crate::outln!();
+}
+
+/// `path (depth 4, follows symlinks)`
+pub fn describe(dir: &SearchDir) -> String {
+    let mut settings = vec![match dir.depth {
+        Some(depth) => format!("depth {}", depth),
+        None => "any depth".to_string(),
+    }];
+    if dir.follow_symlinks {
+        settings.push("follows symlinks".to_string());
+    }
+    format!("{} ({})", dir.path.display(), settings.join(", "))
+}
+
+/// Fingerprint of the settings that decide what searches and scans find:
+/// the directories, scan roots, exclude rules and `respect_gitignore`
+pub fn config_hash() -> Result<String> {
+    let (exclude, respect_gitignore) = load_user_config()?
+        .map(|c| (c.exclude.as_ref(), c.respect_gitignore))
+        .unwrap_or_default();
+    let settings = format!(
+        "{:?}\n{:?}\n{:?}\n{:?}",
+        get_search_dirs()?,
+        get_scan_roots()?,
+        exclude,
+        respect_gitignore
+    );
+
+    // FNV-1a, which unlike std's hasher is the same in every build
+    let hash = settings.bytes().fold(0xcbf29ce484222325_u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    });
+    Ok(format!("{:016x}", hash))
+}
+
+/// Seconds after which a scan is refreshed, or `None` if never
+pub fn cache_max_age() -> Result<Option<u64>> {
+    let hours = load_user_config()?
+        .and_then(|c| c.cache_max_age_hours)
+        .unwrap_or(DEFAULT_CACHE_MAX_AGE_HOURS);
+    Ok((hours > 0).then(|| hours.saturating_mul(3600)))
+}
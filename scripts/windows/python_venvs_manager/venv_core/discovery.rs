@@ -0,0 +1,559 @@
+//! Searching directories and the home folder for environments
+
+use crate::Options;
+use crate::cache::{load_cache, update_cache};
+use crate::conda;
+use crate::config::{SearchDir, describe, get_config_path, get_scan_roots};
+use crate::environment::{
+    Environment, FUZZY_RANK, detect_environment_at_path, is_generic_dir_name,
+};
+use crate::error::{Error, Result};
+use crate::exclude::Excludes;
+use crate::index::{ScanIndex, scan_threads, walk};
+use crate::managers::{central_env_dirs, link_projects};
+use crate::matching::{fuzzy_rank, is_close, suggestions};
+use crate::output::{print_debug, print_info, print_warning};
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use walkdir::{DirEntry, WalkDir};
+
+/// How deep below each search directory environments are looked for, unless
+/// the config file sets a depth for it
+pub const MAX_SEARCH_DEPTH: usize = 4;
+
+/// Whether a walk descends into `entry`: anything but a directory the
+/// exclude rules skip. The directory a walk starts in is always searched.
+fn keep(entry: &DirEntry, root: &Path, excludes: &Excludes, opts: &Options) -> bool {
+    if entry.depth() == 0 || !entry.file_type().is_dir() {
+        return true;
+    }
+    match excludes.reason(entry.path(), root) {
+        None => true,
+        Some(reason) => {
+            if opts.verbose {
+                print_skipped(entry.path(), &reason, opts);
+            }
+            false
+        }
+    }
+}
+
+fn print_skipped(path: &Path, reason: &str, opts: &Options) {
+    print_debug(
+        &format!("Skipped {} ({})", path.display(), reason),
+        opts.no_color,
+    );
+}
+
+/// Finds the environment `env_name` refers to: its name, a qualified
+/// `project/dir` name or a bare directory name (case-insensitive), else the
+/// single environment it is a prefix, substring or subsequence of. Looks in
+/// the cache first, then in `search_dirs`; an unreadable cache is reported
+/// and skipped. A miss suggests names that look like a typo of `env_name`.
+/// The cache is updated with what the lookup saw: vanished environments are
+/// dropped and those found in `search_dirs` added.
+pub fn find_environment(
+    env_name: &str,
+    cache_file: &Path,
+    search_dirs: &[SearchDir],
+    opts: &Options,
+) -> Result<Environment> {
+    // Every environment seen, matching or not, for ranking and suggestions
+    let mut known: Vec<Environment> = Vec::new();
+    // Cached environments no longer on disk
+    let mut gone: Vec<PathBuf> = Vec::new();
+
+    // Try cache first
+    if cache_file.exists() {
+        if opts.verbose {
+            print_debug("Checking cache...", opts.no_color);
+        }
+        match load_cache(cache_file, opts) {
+            Err(e) => print_warning(&format!("Ignoring cache: {}", e), opts.no_color),
+            Ok(environments) => {
+                for env in environments {
+                    if !env.exists() {
+                        if opts.verbose {
+                            print_debug(
+                                &format!("Cached path no longer valid: {}", env.path.display()),
+                                opts.no_color,
+                            );
+                        }
+                        gone.push(env.path);
+                        continue;
+                    }
+                    if opts.verbose && env.match_rank(env_name).is_some() {
+                        print_debug(
+                            &format!(
+                                "Found in cache: {} ({}) at {}",
+                                env.name,
+                                env.env_type,
+                                env.path.display()
+                            ),
+                            opts.no_color,
+                        );
+                    }
+                    known.push(env);
+                }
+                // A loose match may be beaten by an exact one not cached yet
+                if known
+                    .iter()
+                    .any(|e| e.match_rank(env_name).is_some_and(|r| r < FUZZY_RANK))
+                {
+                    update_cache(cache_file, &gone, &[], opts);
+                    return pick_best(env_name, dedup_environments(known, opts));
+                }
+            }
+        }
+    }
+
+    let cached = known.len();
+
+    // Search predefined directories recursively
+    if opts.verbose {
+        print_debug("Searching predefined directories...", opts.no_color);
+    }
+    let excludes = Excludes::load()?;
+
+    // Only directories that could carry the name are inspected: those whose
+    // name matches or resembles the last component of the query, and
+    // generic ones named after their project
+    let last = env_name
+        .trim_end_matches(['/', '\\'])
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or(env_name);
+
+    for dir in search_dirs {
+        if !dir.path.exists() {
+            if opts.verbose {
+                print_debug(
+                    &format!("Directory not found: \"{}\"", dir.path.display()),
+                    opts.no_color,
+                );
+            }
+            continue;
+        }
+
+        if opts.verbose {
+            print_debug(&format!("Searching {}...", describe(dir)), opts.no_color);
+        }
+
+        for entry in WalkDir::new(&dir.path)
+            .max_depth(dir.depth.unwrap_or(usize::MAX))
+            .follow_links(dir.follow_symlinks)
+            .into_iter()
+            .filter_entry(|e| keep(e, &dir.path, &excludes, opts))
+        {
+            let entry = match entry {
+                Ok(e) => e,
+                Err(_) => continue,
+            };
+            if !entry.file_type().is_dir() {
+                continue;
+            }
+            let name = entry.file_name().to_string_lossy();
+            if !is_generic_dir_name(&name)
+                && fuzzy_rank(&name, last).is_none()
+                && !is_close(&name, last)
+            {
+                continue;
+            }
+            if let Some(env) = detect_environment_at_path(entry.path())
+                && !known.iter().any(|k| k.path == env.path)
+            {
+                if opts.verbose && env.match_rank(env_name).is_some() {
+                    print_debug(
+                        &format!(
+                            "Found {} environment at: {}",
+                            env.env_type,
+                            entry.path().display()
+                        ),
+                        opts.no_color,
+                    );
+                }
+                known.push(env);
+            }
+        }
+    }
+
+    for env in scan_manager_dirs(opts)
+        .into_iter()
+        .chain(scan_conda_envs(opts))
+    {
+        if !known.iter().any(|k| k.path == env.path) {
+            known.push(env);
+        }
+    }
+
+    update_cache(cache_file, &gone, &known[cached..], opts);
+    pick_best(env_name, dedup_environments(known, opts))
+}
+
+/// The candidate `query` names best (see [`Environment::match_rank`]). Fails
+/// if several share the best rank, or if none matches, in which case the
+/// candidates closest to a typo of `query` are suggested.
+pub fn pick_best(query: &str, candidates: Vec<Environment>) -> Result<Environment> {
+    let Some(best) = candidates.iter().filter_map(|e| e.match_rank(query)).min() else {
+        return Err(Error::NotFound {
+            name: query.to_string(),
+            suggestions: suggestions(query, candidates.iter().map(|e| e.name.as_str()), 5),
+        });
+    };
+
+    let mut matches: Vec<Environment> = Vec::new();
+    for env in candidates {
+        if env.match_rank(query) == Some(best) && !matches.iter().any(|m| m.path == env.path) {
+            matches.push(env);
+        }
+    }
+
+    if matches.len() == 1 {
+        Ok(matches.remove(0))
+    } else {
+        Err(Error::Ambiguous {
+            name: query.to_string(),
+            candidates: matches,
+        })
+    }
+}
+
+/// `environments` with each environment listed once, sorted by name, then
+/// path. Entries whose paths resolve to the same directory (overlapping
+/// search directories, symlinks, junctions) are merged into the one reached
+/// without a symlink, keeping the latest `last_used`.
+pub fn dedup_environments(environments: Vec<Environment>, opts: &Options) -> Vec<Environment> {
+    let canonical: Vec<PathBuf> = environments
+        .par_iter()
+        .map(|env| fs::canonicalize(&env.path).unwrap_or_else(|_| env.path.clone()))
+        .collect();
+
+    let mut groups: Vec<Vec<Environment>> = Vec::new();
+    let mut group_of: HashMap<PathBuf, usize> = HashMap::new();
+    for (env, path) in environments.into_iter().zip(canonical) {
+        let index = *group_of.entry(path).or_insert_with(|| {
+            groups.push(Vec::new());
+            groups.len() - 1
+        });
+        groups[index].push(env);
+    }
+
+    let mut unique: Vec<Environment> = groups
+        .into_iter()
+        .map(|mut group| {
+            let keep = group
+                .iter()
+                .position(|env| !through_symlink(&env.path))
+                .unwrap_or(0);
+            let mut kept = group.remove(keep);
+            for duplicate in group {
+                if opts.verbose {
+                    let message = if duplicate.path == kept.path {
+                        format!("{} was found more than once", kept.path.display())
+                    } else {
+                        format!("{} is {}", duplicate.path.display(), kept.path.display())
+                    };
+                    print_debug(&format!("Merged duplicate: {}", message), opts.no_color);
+                }
+                kept.last_used = kept.last_used.max(duplicate.last_used);
+                if kept.project.is_none() {
+                    kept.project = duplicate.project;
+                }
+            }
+            kept
+        })
+        .collect();
+
+    unique.sort_by(|a, b| {
+        a.name
+            .to_lowercase()
+            .cmp(&b.name.to_lowercase())
+            .then_with(|| a.name.cmp(&b.name))
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    unique
+}
+
+/// Whether `path` or a folder above it is a symlink (or junction)
+fn through_symlink(path: &Path) -> bool {
+    path.ancestors()
+        .any(|dir| fs::symlink_metadata(dir).is_ok_and(|m| m.file_type().is_symlink()))
+}
+
+/// Every environment within reach of the given directories
+pub fn scan_predefined_dirs(dirs: &[SearchDir], opts: &Options) -> Result<Vec<Environment>> {
+    let excludes = Excludes::load()?;
+    let mut environments = Vec::new();
+
+    for dir in dirs {
+        if !dir.path.exists() {
+            if opts.verbose {
+                print_debug(
+                    &format!("Directory not found: \"{}\"", dir.path.display()),
+                    opts.no_color,
+                );
+            }
+            continue;
+        }
+
+        if opts.verbose {
+            print_debug(&format!("Searching {}...", describe(dir)), opts.no_color);
+        }
+
+        for entry in WalkDir::new(&dir.path)
+            .max_depth(dir.depth.unwrap_or(usize::MAX))
+            .follow_links(dir.follow_symlinks)
+            .into_iter()
+            .filter_entry(|e| keep(e, &dir.path, &excludes, opts))
+        {
+            let entry = match entry {
+                Ok(e) => e,
+                Err(_) => continue,
+            };
+            if !entry.file_type().is_dir() {
+                continue;
+            }
+
+            if let Some(env) = detect_environment_at_path(entry.path()) {
+                if opts.verbose {
+                    print_debug(
+                        &format!("Found: {} ({})", env.name, env.env_type),
+                        opts.no_color,
+                    );
+                }
+                environments.push(env);
+            }
+        }
+    }
+
+    environments.extend(scan_manager_dirs(opts));
+    environments.extend(scan_conda_envs(opts));
+
+    Ok(dedup_environments(environments, opts))
+}
+
+/// Every environment in the central directories of Poetry, Pipenv, PDM and
+/// Hatch. These are walked without the usual exclusions, since they often
+/// live below a `cache` folder.
+pub fn scan_manager_dirs(opts: &Options) -> Vec<Environment> {
+    let mut environments = Vec::new();
+
+    for (manager, dir) in central_env_dirs() {
+        if opts.verbose {
+            print_debug(
+                &format!(
+                    "Searching {} environments in \"{}\"...",
+                    manager.as_str(),
+                    dir.display()
+                ),
+                opts.no_color,
+            );
+        }
+
+        for entry in WalkDir::new(&dir)
+            .min_depth(manager.central_depth())
+            .max_depth(manager.central_depth())
+            .follow_links(false)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_dir())
+        {
+            if let Some(env) = detect_environment_at_path(entry.path()) {
+                if opts.verbose {
+                    print_debug(
+                        &format!("Found: {} ({})", env.name, env.env_type),
+                        opts.no_color,
+                    );
+                }
+                environments.push(env);
+            }
+        }
+    }
+
+    environments
+}
+
+/// Every conda, mamba and micromamba environment the installations and
+/// their config files know of (see [`conda::environment_paths`])
+pub fn scan_conda_envs(opts: &Options) -> Vec<Environment> {
+    if opts.verbose {
+        print_debug("Looking up conda environments...", opts.no_color);
+    }
+
+    conda::environment_paths()
+        .iter()
+        .filter_map(|path| detect_environment_at_path(path))
+        .inspect(|env| {
+            if opts.verbose {
+                print_debug(
+                    &format!(
+                        "Found: {} ({}) at {}",
+                        env.name,
+                        env.env_type,
+                        env.path.display()
+                    ),
+                    opts.no_color,
+                );
+            }
+        })
+        .collect()
+}
+
+/// Every environment with a `pyvenv.cfg` anywhere under the scan roots (the
+/// home folder unless configured),
+/// every pixi environment, every conda environment, and every environment in
+/// the managers' central directories, linked to the projects found along the
+/// way. Directories unchanged since `previous` was recorded are not read
+/// again; the returned indexes record the listings of this scan, one per
+/// root.
+pub fn scan_all_venvs(
+    previous: &[ScanIndex],
+    opts: &Options,
+) -> Result<(Vec<Environment>, Vec<ScanIndex>)> {
+    let roots = get_scan_roots()?;
+    if roots.is_empty() {
+        // Scanning nothing would replace the cache with an empty one
+        return Err(Error::Config {
+            path: get_config_path()?,
+            line: None,
+            column: None,
+            message:
+                "nothing to scan: USERPROFILE and HOME are unset and no scan_roots are configured"
+                    .to_string(),
+        });
+    }
+    let excludes = Excludes::load()?;
+
+    if opts.verbose {
+        print_debug(
+            "Scanning for pyvenv.cfg files in user directory...",
+            opts.no_color,
+        );
+        print_debug(
+            "Using parallel scanning for maximum speed...",
+            opts.no_color,
+        );
+    } else {
+        print_info("Scanning...", opts.no_color);
+    }
+
+    let threads = scan_threads();
+    let mut indexes = Vec::new();
+    let mut found: Vec<PathBuf> = Vec::new();
+    for root in &roots {
+        if !root.path.exists() {
+            if opts.verbose {
+                print_debug(
+                    &format!("Directory not found: \"{}\"", root.path.display()),
+                    opts.no_color,
+                );
+            }
+            continue;
+        }
+
+        let started = Instant::now();
+        let walked = walk(root, &root.path, previous, &excludes, threads);
+        if opts.verbose {
+            for (path, reason) in &walked.skipped {
+                print_skipped(path, reason, opts);
+            }
+            let stats = walked.stats;
+            print_debug(
+                &format!(
+                    "Walked {} directories of {} with {} threads in {:.2?}: read {}, reused {} unchanged",
+                    stats.read + stats.reused,
+                    describe(root),
+                    threads,
+                    started.elapsed(),
+                    stats.read,
+                    stats.reused
+                ),
+                opts.no_color,
+            );
+        }
+        indexes.push(walked.index);
+        found.extend(walked.found);
+    }
+    // Roots may overlap
+    found.sort();
+    found.dedup();
+
+    let mut pyvenv_files: Vec<PathBuf> = Vec::new();
+    // Directories holding a pyproject.toml, to link managed environments to
+    let mut projects: Vec<PathBuf> = Vec::new();
+    for path in found {
+        let name = path.file_name().unwrap_or_default();
+        // pixi environments are conda prefixes without a pyvenv.cfg
+        if name == "pyvenv.cfg" || (name == "conda-meta" && is_pixi_env(&path)) {
+            pyvenv_files.push(path);
+        } else if name == "pyproject.toml"
+            && let Some(parent) = path.parent()
+        {
+            projects.push(parent.to_path_buf());
+        }
+    }
+
+    let scan_count = pyvenv_files.len();
+    let started = Instant::now();
+
+    if opts.verbose {
+        print_debug(
+            &format!("Found {} pyvenv.cfg files, processing...", scan_count),
+            opts.no_color,
+        );
+    }
+
+    // Process pyvenv.cfg files in parallel
+    let mut environments: Vec<Environment> = pyvenv_files
+        .par_iter()
+        .filter_map(|cfg_path| {
+            let env = detect_environment_at_path(cfg_path.parent()?)?;
+            if opts.verbose {
+                print_debug(
+                    &format!(
+                        "Found: {} ({}) at {}",
+                        env.name,
+                        env.env_type,
+                        env.path.display()
+                    ),
+                    opts.no_color,
+                );
+            }
+            Some(env)
+        })
+        .collect();
+
+    environments.extend(scan_manager_dirs(opts));
+    environments.extend(scan_conda_envs(opts));
+    let mut environments = dedup_environments(environments, opts);
+    link_projects(&mut environments, &projects);
+
+    if opts.verbose {
+        print_debug(
+            &format!("Detected environments in {:.2?}", started.elapsed()),
+            opts.no_color,
+        );
+        print_debug(
+            &format!("Scan complete. Found {} environments.", environments.len()),
+            opts.no_color,
+        );
+    } else {
+        print_info(
+            &format!("Scan complete. Checked {} files.", scan_count),
+            opts.no_color,
+        );
+    }
+
+    Ok((environments, indexes))
+}
+
+/// Whether `conda_meta` is the `conda-meta` folder of `.pixi/envs/<env>`
+pub(crate) fn is_pixi_env(conda_meta: &Path) -> bool {
+    conda_meta
+        .parent()
+        .and_then(Path::parent)
+        .is_some_and(|envs| envs.ends_with(Path::new(".pixi").join("envs")))
+}
@@ -0,0 +1,313 @@
+//! Auditing the cached environment inventory (`venv doctor`)
+//!
+//! Every cached environment is looked at again on disk with
+//! [`detect_environment_at_path`], and each problem found is reported with a
+//! suggested fix.
+
+use crate::config::SearchDir;
+use crate::environment::{Environment, Layout, detect_environment_at_path};
+use crate::managers::central_env_dirs;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::time::UNIX_EPOCH;
+
+/// Days without a recorded activation after which an environment is unused
+pub const DEFAULT_UNUSED_DAYS: u64 = 90;
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// End-of-life dates of Python minor versions still commonly found
+const PYTHON_EOL: &[(u32, u32, &str)] = &[
+    (2, 7, "2020-01-01"),
+    (3, 6, "2021-12-23"),
+    (3, 7, "2023-06-27"),
+    (3, 8, "2024-10-07"),
+    (3, 9, "2025-10-31"),
+    (3, 10, "2026-10-31"),
+    (3, 11, "2027-10-31"),
+    (3, 12, "2028-10-31"),
+    (3, 13, "2029-10-31"),
+    (3, 14, "2030-10-31"),
+];
+
+/// What a [`Finding`] is about
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Check {
+    /// The environment cannot run Python
+    Broken,
+    /// Several environments share a name, so `ape <name>` is ambiguous
+    DuplicateName,
+    /// The environment's Python no longer gets security fixes
+    EndOfLife,
+    /// Not activated for longer than the threshold
+    Unused,
+    /// Only found by a full scan, not by a quick search
+    OutsideSearchDirs,
+    /// The cache lists an environment that no longer exists
+    Vanished,
+}
+
+impl Check {
+    /// Section heading in the text report
+    pub fn title(self) -> &'static str {
+        match self {
+            Check::Broken => "Broken environments",
+            Check::DuplicateName => "Duplicate names",
+            Check::EndOfLife => "End-of-life Python",
+            Check::Unused => "Unused environments",
+            Check::OutsideSearchDirs => "Outside the searched directories",
+            Check::Vanished => "Vanished cache entries",
+        }
+    }
+
+    /// Every check, in report order
+    pub const ALL: [Check; 6] = [
+        Check::Vanished,
+        Check::Broken,
+        Check::EndOfLife,
+        Check::DuplicateName,
+        Check::Unused,
+        Check::OutsideSearchDirs,
+    ];
+}
+
+/// One problem and how to fix it
+#[derive(Debug, Clone, Serialize)]
+pub struct Finding {
+    pub check: Check,
+    pub name: String,
+    /// Environments involved (several for a duplicate name)
+    pub paths: Vec<PathBuf>,
+    pub message: String,
+    pub fix: String,
+}
+
+/// Everything `venv doctor` found
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    /// Number of cached environments that were audited
+    pub environments: usize,
+    pub findings: Vec<Finding>,
+}
+
+/// Thresholds and context of an audit
+#[derive(Debug, Clone)]
+pub struct DoctorOptions {
+    /// Days without activation before an environment counts as unused
+    pub unused_days: u64,
+    /// Directories a quick search looks in
+    pub search_dirs: Vec<SearchDir>,
+    /// Config file to suggest adding directories to
+    pub config_path: PathBuf,
+    /// Seconds since the Unix epoch to audit against
+    pub now: u64,
+}
+
+/// Audits `cached` (the cache contents) against the disk
+pub fn diagnose(cached: &[Environment], options: &DoctorOptions) -> Report {
+    let mut findings = Vec::new();
+    let mut present: Vec<Environment> = Vec::new();
+
+    for entry in cached {
+        match detect_environment_at_path(&entry.path) {
+            Some(mut env) => {
+                env.last_used = entry.last_used;
+                present.push(env);
+            }
+            None => findings.push(Finding {
+                check: Check::Vanished,
+                name: entry.name.clone(),
+                paths: vec![entry.path.clone()],
+                message: "no environment at this path anymore".to_string(),
+                fix: "Run 'venv scan' to refresh the cache".to_string(),
+            }),
+        }
+    }
+
+    for env in &present {
+        findings.extend(broken(env));
+        findings.extend(end_of_life(env, options.now));
+        findings.extend(unused(env, options));
+        findings.extend(outside_search_dirs(env, options));
+    }
+    findings.extend(duplicate_names(&present));
+
+    findings.sort_by_key(|f| Check::ALL.iter().position(|c| *c == f.check));
+    Report {
+        environments: cached.len(),
+        findings,
+    }
+}
+
+fn finding(check: Check, env: &Environment, message: String, fix: String) -> Finding {
+    Finding {
+        check,
+        name: env.name.clone(),
+        paths: vec![env.path.clone()],
+        message,
+        fix,
+    }
+}
+
+fn broken(env: &Environment) -> Option<Finding> {
+    let problems = env.health.problems();
+    if problems.is_empty() {
+        return None;
+    }
+    let message = problems
+        .iter()
+        .map(|p| p.to_string())
+        .collect::<Vec<_>>()
+        .join("; ");
+    Some(finding(
+        Check::Broken,
+        env,
+        message,
+        format!(
+            "Recreate it with an installed Python, or delete {}",
+            env.path.display()
+        ),
+    ))
+}
+
+fn end_of_life(env: &Environment, now: u64) -> Option<Finding> {
+    let version = env.pyvenv.as_ref()?.version.as_deref()?;
+    let mut parts = version.split('.').map(|p| p.parse::<u32>().ok());
+    let (major, minor) = (parts.next()??, parts.next()??);
+
+    let eol = PYTHON_EOL
+        .iter()
+        .find(|(ma, mi, _)| *ma == major && *mi == minor)
+        .map(|(_, _, date)| *date);
+    match eol {
+        Some(date) if parse_date(date)? > now => return None,
+        // Anything older than the table is long past its end of life
+        None if major > 3 || (major == 3 && minor >= 6) => return None,
+        _ => {}
+    }
+
+    let supported = PYTHON_EOL
+        .iter()
+        .find(|(_, _, date)| parse_date(date).is_some_and(|d| d > now))
+        .map(|(ma, mi, _)| format!("{}.{}", ma, mi))
+        .unwrap_or_else(|| "a supported version".to_string());
+    let when = match eol {
+        Some(date) => format!("on {}", date),
+        None => "long ago".to_string(),
+    };
+    Some(finding(
+        Check::EndOfLife,
+        env,
+        format!("Python {} reached end of life {}", version, when),
+        format!("Recreate it with Python {} or newer", supported),
+    ))
+}
+
+fn unused(env: &Environment, options: &DoctorOptions) -> Option<Finding> {
+    let limit = options.unused_days.saturating_mul(SECONDS_PER_DAY);
+    let (since, message) = match env.last_used {
+        Some(used) => (used, "last activated"),
+        // Never activated since usage is recorded: count from its creation
+        None => (created(env)?, "never activated, created"),
+    };
+    let age = options.now.checked_sub(since)?;
+    if age <= limit {
+        return None;
+    }
+    Some(finding(
+        Check::Unused,
+        env,
+        format!("{} {} days ago", message, age / SECONDS_PER_DAY),
+        format!("Delete {} if it is no longer needed", env.path.display()),
+    ))
+}
+
+/// When the environment was created, going by its `pyvenv.cfg` or
+/// `conda-meta`
+fn created(env: &Environment) -> Option<u64> {
+    let marker = match env.layout {
+        Layout::Conda => env.path.join("conda-meta"),
+        _ => env.path.join("pyvenv.cfg"),
+    };
+    let modified = marker.metadata().ok()?.modified().ok()?;
+    modified
+        .duration_since(UNIX_EPOCH)
+        .ok()
+        .map(|d| d.as_secs())
+}
+
+fn outside_search_dirs(env: &Environment, options: &DoctorOptions) -> Option<Finding> {
+    // Conda and central manager environments are found where their tools keep them
+    if env.layout == Layout::Conda
+        || central_env_dirs()
+            .iter()
+            .any(|(_, dir)| env.path.starts_with(dir))
+    {
+        return None;
+    }
+    if options.search_dirs.iter().any(|dir| dir.reaches(&env.path)) {
+        return None;
+    }
+    let parent = env.path.parent().unwrap_or(&env.path);
+    Some(finding(
+        Check::OutsideSearchDirs,
+        env,
+        "not within reach of a searched directory, so only 'venv scan' finds it".to_string(),
+        format!(
+            "Add {} to directories in {}",
+            parent.display(),
+            options.config_path.display()
+        ),
+    ))
+}
+
+fn duplicate_names(present: &[Environment]) -> Vec<Finding> {
+    let mut findings: Vec<Finding> = Vec::new();
+    for env in present {
+        if findings
+            .iter()
+            .any(|f| f.name.eq_ignore_ascii_case(&env.name))
+        {
+            continue;
+        }
+        let same: Vec<&Environment> = present
+            .iter()
+            .filter(|e| e.name.eq_ignore_ascii_case(&env.name))
+            .collect();
+        if same.len() < 2 {
+            continue;
+        }
+        let qualified: Vec<String> = same.iter().map(|e| e.qualified_name()).collect();
+        findings.push(Finding {
+            check: Check::DuplicateName,
+            name: env.name.clone(),
+            paths: same.iter().map(|e| e.path.clone()).collect(),
+            message: format!("{} environments are named \"{}\"", same.len(), env.name),
+            fix: format!(
+                "Activate them as {}, or give each its own prompt or folder name",
+                qualified.join(" or ")
+            ),
+        });
+    }
+    findings
+}
+
+/// `YYYY-MM-DD` as seconds since the Unix epoch (UTC midnight)
+fn parse_date(date: &str) -> Option<u64> {
+    let mut parts = date.split('-').map(|p| p.parse::<i64>().ok());
+    let (y, m, d) = (parts.next()??, parts.next()??, parts.next()??);
+    let days = days_from_civil(y, m, d);
+    u64::try_from(days).ok().map(|days| days * SECONDS_PER_DAY)
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian date
+fn days_from_civil(y: i64, m: i64, d: i64) -> i64 {
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (m + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
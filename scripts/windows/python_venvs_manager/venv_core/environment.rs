@@ -0,0 +1,268 @@
+//! Recognizing a virtual environment on disk
+
+use crate::conda;
+use crate::health::{Health, check_health};
+use crate::managers::{Managed, detect_manager, linked_project};
+use crate::matching::fuzzy_rank;
+use crate::pyvenv::{CreatorKind, PyvenvConfig, read_pyvenv_cfg};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Directory names tools give a project's own environment; such an
+/// environment is named after its project instead
+pub const GENERIC_DIR_NAMES: &[&str] = &[".venv", "venv", "env"];
+
+/// First [`Environment::match_rank`] of a loose (non-exact) match
+pub const FUZZY_RANK: u8 = 3;
+
+/// A discovered Python environment, as stored in the cache
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Environment {
+    /// Name used to activate it (`ape <name>`), see [`environment_name`]
+    pub name: String,
+    /// `venv`, `uv`, `conda`, the managing tool (`poetry`, `pipenv`, `pdm`,
+    /// `hatch`, `rye`, `pixi`) or `unknown`
+    pub env_type: String,
+    /// Root directory of the environment
+    pub path: PathBuf,
+    // Caches written before POSIX support only ever held Windows layouts
+    #[serde(default)]
+    pub layout: Layout,
+    /// Parsed `pyvenv.cfg` (conda environments have none)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pyvenv: Option<PyvenvConfig>,
+    /// Project directory the environment belongs to, for managed ones and
+    /// virtualenvwrapper ones linked by `.project`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project: Option<PathBuf>,
+    /// Whether it could run Python when it was discovered
+    #[serde(default)]
+    pub health: Health,
+    /// When `ape`/`spe` last activated it, in seconds since the Unix epoch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_used: Option<u64>,
+}
+
+/// Seconds since the Unix epoch, as `last_used` and the cache record times
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// On-disk layout of a virtual environment
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Layout {
+    /// `Scripts\activate.bat`, `Scripts\python.exe`, `Lib\site-packages`
+    #[default]
+    Windows,
+    /// `bin/activate`, `bin/python`, `lib/pythonX.Y/site-packages`
+    Posix,
+    /// A conda prefix (`conda-meta`), activated by its installation's scripts
+    Conda,
+}
+
+impl Layout {
+    /// Directory holding the interpreter and activation scripts
+    pub fn bin_dir(self) -> &'static str {
+        match self {
+            Layout::Windows => "Scripts",
+            Layout::Posix => "bin",
+            Layout::Conda if cfg!(windows) => "Scripts",
+            Layout::Conda => "bin",
+        }
+    }
+}
+
+impl Environment {
+    /// Activation script the CMD/PowerShell wrappers (or a POSIX shell on
+    /// other platforms) run for this environment. For conda this is the
+    /// installation's script, which takes the environment as its argument.
+    pub fn activate_script(&self) -> PathBuf {
+        match self.layout {
+            Layout::Windows if cfg!(windows) => self.path.join("Scripts").join("activate.bat"),
+            // Windows venvs also ship a POSIX `activate` for Git Bash / WSL
+            Layout::Windows => self.path.join("Scripts").join("activate"),
+            Layout::Posix => self.path.join("bin").join("activate"),
+            Layout::Conda => {
+                let root = conda::installation_for(&self.path).unwrap_or_else(|| self.path.clone());
+                if cfg!(windows) {
+                    root.join("Scripts").join("activate.bat")
+                } else {
+                    root.join("bin").join("activate")
+                }
+            }
+        }
+    }
+
+    /// Whether the environment is still on disk where it was found
+    pub fn exists(&self) -> bool {
+        match self.layout {
+            Layout::Conda => conda::is_conda_env(&self.path),
+            _ => self.activate_script().exists(),
+        }
+    }
+
+    /// `project/dir`, e.g. `billing/.venv`, which tells apart environments
+    /// that share a directory name
+    pub fn qualified_name(&self) -> String {
+        let dir = file_name(&self.path);
+        match self.path.parent().map(file_name).filter(|p| !p.is_empty()) {
+            Some(parent) => format!("{}/{}", parent, dir),
+            None => dir,
+        }
+    }
+
+    /// How well `query` names this environment, lower is better: 0 for its
+    /// qualified name, 1 for its name, 2 for its bare directory name or its
+    /// project's name (for a generic directory or a linked project), then
+    /// [`FUZZY_RANK`] and up
+    /// for a prefix, substring or subsequence of its name. `None` for no match
+    pub fn match_rank(&self, query: &str) -> Option<u8> {
+        let query = query.replace('\\', "/");
+        let query = query.trim_end_matches('/');
+        let dir = file_name(&self.path);
+        let project = self.path.parent().map(file_name).unwrap_or_default();
+        if self.qualified_name().eq_ignore_ascii_case(query) {
+            Some(0)
+        } else if self.name.eq_ignore_ascii_case(query) {
+            Some(1)
+        } else if dir.eq_ignore_ascii_case(query)
+            || (is_generic_dir_name(&dir) && project.eq_ignore_ascii_case(query))
+            || self
+                .project
+                .as_deref()
+                .is_some_and(|p| file_name(p).eq_ignore_ascii_case(query))
+        {
+            Some(2)
+        } else {
+            fuzzy_rank(&self.name, query).map(|rank| FUZZY_RANK + rank)
+        }
+    }
+}
+
+pub fn is_generic_dir_name(name: &str) -> bool {
+    GENERIC_DIR_NAMES
+        .iter()
+        .any(|g| g.eq_ignore_ascii_case(name))
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// Name for the environment at `path`: its directory name, unless that is
+/// generic (`.venv`, `venv`, `env`), in which case the `prompt` from
+/// `pyvenv.cfg` or else the project (parent) directory name
+pub fn environment_name(path: &Path, pyvenv: Option<&PyvenvConfig>) -> String {
+    let dir = file_name(path);
+    if !is_generic_dir_name(&dir) {
+        return dir;
+    }
+    // `.` stands for the current directory, not a name
+    if let Some(prompt) = pyvenv
+        .and_then(|cfg| cfg.prompt.as_deref())
+        .filter(|p| *p != ".")
+    {
+        return prompt.to_string();
+    }
+    match path.parent().map(file_name).filter(|p| !p.is_empty()) {
+        Some(project) => project,
+        None => dir,
+    }
+}
+
+/// Returns the environment rooted at `path`, or `None` if `path` is not one
+pub fn detect_environment_at_path(path: &Path) -> Option<Environment> {
+    let layout = detect_layout(path)?;
+    path.file_name()?;
+    let pyvenv = read_pyvenv_cfg(path);
+    let managed = detect_manager(path);
+    let name = match &managed {
+        Some(Managed {
+            name: Some(name), ..
+        }) => name.clone(),
+        // Conda's own name for an installation's root environment
+        _ if layout == Layout::Conda && conda::is_base(path) => "base".to_string(),
+        _ => environment_name(path, pyvenv.as_ref()),
+    };
+    let env_type = match &managed {
+        Some(m) => m.manager.as_str().to_string(),
+        None => detect_env_type(path, pyvenv.as_ref()),
+    };
+    let health = check_health(path, layout, pyvenv.as_ref());
+
+    Some(Environment {
+        name,
+        env_type,
+        path: path.to_path_buf(),
+        layout,
+        pyvenv,
+        project: managed
+            .and_then(|m| m.project)
+            .or_else(|| linked_project(path)),
+        health,
+        last_used: None,
+    })
+}
+
+pub fn detect_layout(path: &Path) -> Option<Layout> {
+    if conda::is_conda_env(path) {
+        return Some(Layout::Conda);
+    }
+
+    if path.join("Scripts").join("activate.bat").exists() {
+        return Some(Layout::Windows);
+    }
+
+    // `bin/python` alone is no venv: prefixes like /usr and ~/.local have it
+    // too, often with a site-packages, but never `pyvenv.cfg` or `activate`
+    let bin = path.join("bin");
+    if bin.join("activate").exists() || (path.join("pyvenv.cfg").is_file() && bin.is_dir()) {
+        return Some(Layout::Posix);
+    }
+
+    None
+}
+
+/// Checks for `lib/pythonX.Y/site-packages` (or `lib64/...`)
+pub(crate) fn has_posix_site_packages(path: &Path) -> bool {
+    ["lib", "lib64"].iter().any(|lib| {
+        fs::read_dir(path.join(lib))
+            .map(|entries| {
+                entries.filter_map(|e| e.ok()).any(|e| {
+                    e.file_name().to_string_lossy().starts_with("python")
+                        && e.path().join("site-packages").is_dir()
+                })
+            })
+            .unwrap_or(false)
+    })
+}
+
+pub fn detect_env_type(path: &Path, pyvenv: Option<&PyvenvConfig>) -> String {
+    // Check for conda
+    if path.join("conda-meta").exists() {
+        return "conda".to_string();
+    }
+
+    // Check for uv
+    if pyvenv
+        .and_then(|cfg| cfg.creator.as_ref())
+        .is_some_and(|c| c.kind == CreatorKind::Uv)
+    {
+        return "uv".to_string();
+    }
+
+    // Default to venv
+    if detect_layout(path).is_some() {
+        return "venv".to_string();
+    }
+
+    "unknown".to_string()
+}
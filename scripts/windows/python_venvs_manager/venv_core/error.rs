@@ -0,0 +1,146 @@
+//! Failures reported by every operation, each with its own process exit code
+
+use crate::environment::Environment;
+use crate::health::Problem;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+/// Everything that can make a command fail
+#[derive(Debug)]
+pub enum Error {
+    /// Invalid command line; each entry is one problem
+    Usage {
+        errors: Vec<String>,
+        /// Command that prints the relevant help, e.g. `ape --help`
+        help_hint: String,
+    },
+    /// No environment matches the requested name
+    NotFound {
+        name: String,
+        /// Known names that look like a misspelling of `name`
+        suggestions: Vec<String>,
+    },
+    /// Several environments match and none can be preferred
+    Ambiguous {
+        name: String,
+        candidates: Vec<Environment>,
+    },
+    /// The environment exists but cannot run Python (see [`crate::health`])
+    Broken {
+        name: String,
+        path: PathBuf,
+        problems: Vec<Problem>,
+    },
+    /// `python_venv_config.toml` could not be read or parsed
+    Config {
+        path: PathBuf,
+        /// 1-based position of a parse error, when known
+        line: Option<usize>,
+        column: Option<usize>,
+        message: String,
+    },
+    /// A directory derived from the home folder (the cache, the config file
+    /// or a `~` path in it) cannot be located because there is none
+    NoHome {
+        /// What the directory is for, e.g. `cache`
+        kind: &'static str,
+        /// Variable that sets it explicitly, e.g. `APE_CACHE`
+        var: &'static str,
+    },
+    /// The cache file could not be read, parsed, written or removed
+    Cache { path: PathBuf, message: String },
+    /// Any other I/O failure (activation scripts, the handoff file, stdin)
+    Io(io::Error),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    /// Process exit code for this kind of failure (0 is success)
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Error::Usage { .. } => 2,
+            Error::NotFound { .. } => 3,
+            Error::Ambiguous { .. } => 4,
+            Error::Config { .. } | Error::NoHome { .. } => 5,
+            Error::Cache { .. } => 6,
+            Error::Io(_) => 7,
+            Error::Broken { .. } => 8,
+        }
+    }
+
+    /// Single usage error pointing at `help_hint`
+    pub fn usage(error: impl Into<String>, help_hint: impl Into<String>) -> Error {
+        Error::Usage {
+            errors: vec![error.into()],
+            help_hint: help_hint.into(),
+        }
+    }
+
+    pub(crate) fn cache(path: impl Into<PathBuf>, message: impl fmt::Display) -> Error {
+        Error::Cache {
+            path: path.into(),
+            message: message.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Usage { errors, .. } => write!(f, "{}", errors.join("; ")),
+            Error::NotFound { name, .. } => write!(f, "Environment \"{}\" not found.", name),
+            Error::Ambiguous { name, candidates } => {
+                write!(f, "\"{}\" matches {} environments.", name, candidates.len())
+            }
+            Error::Broken { name, path, .. } => {
+                write!(
+                    f,
+                    "Environment \"{}\" at {} is broken.",
+                    name,
+                    path.display()
+                )
+            }
+            Error::Config {
+                path,
+                line,
+                column,
+                message,
+            } => {
+                write!(f, "Invalid config file {}", path.display())?;
+                if let Some(line) = line {
+                    write!(f, ", line {}", line)?;
+                    if let Some(column) = column {
+                        write!(f, ", column {}", column)?;
+                    }
+                }
+                write!(f, ": {}", message)
+            }
+            Error::NoHome { kind, var } => write!(
+                f,
+                "Could not determine a {} directory; set {} or HOME.",
+                kind, var
+            ),
+            Error::Cache { path, message } => {
+                write!(f, "Cache file {}: {}", path.display(), message)
+            }
+            Error::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error::Io(e)
+    }
+}
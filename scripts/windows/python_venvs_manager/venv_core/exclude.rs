@@ -0,0 +1,173 @@
+//! Which directories searches and scans skip
+//!
+//! Rules are gitignore-style globs: the [`DEFAULT_EXCLUDES`], then the
+//! `exclude` list of `python_venv_config.toml`, where the last matching rule
+//! wins, so `!cache` brings back a folder the defaults skip. A rule without a
+//! `/` matches a whole folder name at any depth; one with a `/` is relative
+//! to the home folder. With `respect_gitignore`, `.gitignore` and `.ignore`
+//! files in the searched folders are honored too, up to the folder a walk
+//! starts in (none above it, as git stops at the repository root), except
+//! that an environment itself is never skipped: projects routinely ignore
+//! their own `.venv`.
+
+use crate::config::{get_config_path, home_dir, load_user_config};
+use crate::error::{Error, Result};
+use ignore::Match;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+/// Folders skipped unless the config brings them back. Unlike the substring
+/// filter of older versions these match whole names, so `templates` or
+/// `cachetools` are still searched.
+pub const DEFAULT_EXCLUDES: &[&str] = &[
+    "node_modules",
+    ".git",
+    "__pycache__",
+    ".cache",
+    "cache",
+    "caches",
+    "*_cache",
+    "temp",
+    "tmp",
+    ".tmp",
+    ".Trash*",
+    "$RECYCLE.BIN",
+    "System Volume Information",
+];
+
+/// Ignore files read in each folder with `respect_gitignore`, later ones
+/// taking precedence
+const IGNORE_FILES: &[&str] = &[".gitignore", ".ignore"];
+
+/// The exclusion rules in effect
+pub struct Excludes {
+    rules: Gitignore,
+    respect_gitignore: bool,
+    /// Rules of the ignore files of each folder checked so far
+    local: RwLock<HashMap<PathBuf, Option<Arc<Gitignore>>>>,
+}
+
+impl Excludes {
+    /// Defaults plus `patterns` (from `config`, for error messages), matched
+    /// case-insensitively; anchored patterns are relative to `root`
+    pub fn new(
+        root: &Path,
+        patterns: &[String],
+        config: &Path,
+        respect_gitignore: bool,
+    ) -> Result<Excludes> {
+        let mut builder = GitignoreBuilder::new(root);
+        let invalid = |e: ignore::Error| Error::Config {
+            path: config.to_path_buf(),
+            line: None,
+            column: None,
+            message: format!("invalid exclude pattern: {}", e),
+        };
+        builder.case_insensitive(true).map_err(invalid)?;
+        for pattern in DEFAULT_EXCLUDES {
+            builder.add_line(None, pattern).map_err(invalid)?;
+        }
+        for pattern in patterns {
+            builder
+                .add_line(Some(config.to_path_buf()), pattern)
+                .map_err(invalid)?;
+        }
+
+        Ok(Excludes {
+            rules: builder.build().map_err(invalid)?,
+            respect_gitignore,
+            local: RwLock::new(HashMap::new()),
+        })
+    }
+
+    /// The rules of the user's config file
+    pub fn load() -> Result<Excludes> {
+        let config = load_user_config()?;
+        let (patterns, respect_gitignore) = config
+            .map(|c| (c.exclude.clone().unwrap_or_default(), c.respect_gitignore))
+            .unwrap_or_default();
+        Excludes::new(
+            &home_dir().unwrap_or_default(),
+            &patterns,
+            &get_config_path()?,
+            respect_gitignore.unwrap_or(false),
+        )
+    }
+
+    /// Why the directory at `path` is skipped, or `None` to search it. Only
+    /// `path` itself is matched: walks stop at skipped folders, so their
+    /// contents are never checked. Like git at a repository root, ignore
+    /// files are read up to `root`, the folder being walked, and not above it.
+    pub fn reason(&self, path: &Path, root: &Path) -> Option<String> {
+        if let Match::Ignore(glob) = self.rules.matched(path, true) {
+            let source = match glob.from() {
+                Some(file) => file.display().to_string(),
+                None => "the defaults".to_string(),
+            };
+            return Some(format!(
+                "exclude rule \"{}\" from {}",
+                glob.original(),
+                source
+            ));
+        }
+        if !self.respect_gitignore {
+            return None;
+        }
+
+        // The nearest ignore file with an opinion decides
+        for dir in path
+            .ancestors()
+            .skip(1)
+            .take_while(|dir| dir.starts_with(root))
+        {
+            let Some(rules) = self.local_rules(dir) else {
+                continue;
+            };
+            match rules.matched(path, true) {
+                Match::None => continue,
+                Match::Whitelist(_) => return None,
+                Match::Ignore(glob) if !is_environment(path) => {
+                    let file = glob.from().unwrap_or(dir);
+                    return Some(format!("\"{}\" in {}", glob.original(), file.display()));
+                }
+                Match::Ignore(_) => return None,
+            }
+        }
+        None
+    }
+
+    /// The rules of `dir`'s ignore files, read once
+    fn local_rules(&self, dir: &Path) -> Option<Arc<Gitignore>> {
+        if let Some(rules) = self.local.read().ok()?.get(dir) {
+            return rules.clone();
+        }
+
+        let files: Vec<PathBuf> = IGNORE_FILES
+            .iter()
+            .map(|name| dir.join(name))
+            .filter(|file| file.is_file())
+            .collect();
+        let rules = if files.is_empty() {
+            None
+        } else {
+            let mut builder = GitignoreBuilder::new(dir);
+            for file in &files {
+                // A broken line only loses that rule, as with git
+                let _ = builder.add(file);
+            }
+            builder.build().ok().map(Arc::new)
+        };
+
+        if let Ok(mut local) = self.local.write() {
+            local.insert(dir.to_path_buf(), rules.clone());
+        }
+        rules
+    }
+}
+
+/// Whether `path` is the root of an environment
+fn is_environment(path: &Path) -> bool {
+    path.join("pyvenv.cfg").is_file() || path.join("conda-meta").is_dir()
+}
@@ -0,0 +1,184 @@
+//! Checking that an environment can still run Python
+//!
+//! An environment keeps its activation scripts after its base interpreter is
+//! uninstalled or half of it is deleted, so the scripts alone do not show it
+//! works. Each discovered environment is checked for its base interpreter,
+//! its own `python` executable and its `site-packages`.
+
+use crate::Options;
+use crate::environment::{Environment, Layout, has_posix_site_packages};
+use crate::error::{Error, Result};
+use crate::output::print_warning;
+use crate::pyvenv::{PyvenvConfig, read_pyvenv_cfg};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Whether an environment looked usable when it was last checked
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "lowercase")]
+pub enum Health {
+    // Caches written before health checks hold no status
+    #[default]
+    Ok,
+    Broken {
+        problems: Vec<Problem>,
+    },
+}
+
+/// One reason an environment cannot run Python
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum Problem {
+    /// The `home` in `pyvenv.cfg` no longer exists
+    MissingBaseInterpreter { home: PathBuf },
+    /// There is no `python` executable
+    MissingPython,
+    /// The `python` executable is a symlink to a file that is gone
+    DanglingPython { link: PathBuf },
+    /// There is no `site-packages` directory
+    MissingSitePackages,
+}
+
+impl Health {
+    pub fn is_ok(&self) -> bool {
+        matches!(self, Health::Ok)
+    }
+
+    /// `ok` or `broken`, as shown in tables
+    pub fn label(&self) -> &'static str {
+        match self {
+            Health::Ok => "ok",
+            Health::Broken { .. } => "broken",
+        }
+    }
+
+    pub fn problems(&self) -> &[Problem] {
+        match self {
+            Health::Ok => &[],
+            Health::Broken { problems } => problems,
+        }
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::MissingBaseInterpreter { home } => {
+                write!(f, "base interpreter {} no longer exists", home.display())
+            }
+            Problem::MissingPython => write!(f, "python executable is missing"),
+            Problem::DanglingPython { link } => {
+                write!(
+                    f,
+                    "{} points to a file that no longer exists",
+                    link.display()
+                )
+            }
+            Problem::MissingSitePackages => write!(f, "site-packages is missing"),
+        }
+    }
+}
+
+/// Checks the environment rooted at `path`
+pub fn check_health(path: &Path, layout: Layout, pyvenv: Option<&PyvenvConfig>) -> Health {
+    let mut problems = Vec::new();
+
+    // A Windows `C:\...` home is not absolute, nor checkable, from WSL
+    if let Some(home) = pyvenv.and_then(|cfg| cfg.home.as_ref())
+        && home.is_absolute()
+        && !home.is_dir()
+    {
+        problems.push(Problem::MissingBaseInterpreter { home: home.clone() });
+    }
+
+    // A conda environment need not contain Python at all
+    if layout != Layout::Conda || has_conda_python(path) {
+        problems.extend(check_python(path, layout));
+        if !has_site_packages(path, layout) {
+            problems.push(Problem::MissingSitePackages);
+        }
+    }
+
+    if problems.is_empty() {
+        Health::Ok
+    } else {
+        Health::Broken { problems }
+    }
+}
+
+fn check_python(path: &Path, layout: Layout) -> Option<Problem> {
+    let candidates = match layout {
+        Layout::Windows => vec![path.join("Scripts").join("python.exe")],
+        Layout::Conda if cfg!(windows) => vec![path.join("python.exe")],
+        Layout::Posix | Layout::Conda => {
+            vec![
+                path.join("bin").join("python"),
+                path.join("bin").join("python3"),
+            ]
+        }
+    };
+
+    let mut dangling = None;
+    for candidate in candidates {
+        if candidate.is_file() {
+            return None;
+        }
+        // metadata() follows the link and fails, symlink_metadata() does not
+        if dangling.is_none() && fs::symlink_metadata(&candidate).is_ok() {
+            dangling = Some(Problem::DanglingPython { link: candidate });
+        }
+    }
+    Some(dangling.unwrap_or(Problem::MissingPython))
+}
+
+fn has_site_packages(path: &Path, layout: Layout) -> bool {
+    match layout {
+        Layout::Windows => path.join("Lib").join("site-packages").is_dir(),
+        Layout::Conda if cfg!(windows) => path.join("Lib").join("site-packages").is_dir(),
+        Layout::Posix | Layout::Conda => has_posix_site_packages(path),
+    }
+}
+
+/// Whether `conda-meta` records a `python` package
+fn has_conda_python(path: &Path) -> bool {
+    fs::read_dir(path.join("conda-meta"))
+        .map(|entries| {
+            entries.filter_map(|e| e.ok()).any(|e| {
+                e.file_name()
+                    .to_string_lossy()
+                    .strip_prefix("python-")
+                    .is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_digit()))
+            })
+        })
+        .unwrap_or(false)
+}
+
+/// Re-checks `env` right before activation, since the cached health may be
+/// stale. A broken environment is refused unless `force` is set, in which
+/// case each problem is only reported.
+pub fn ensure_healthy(env: &Environment, force: bool, opts: &Options) -> Result<()> {
+    let health = check_health(&env.path, env.layout, read_pyvenv_cfg(&env.path).as_ref());
+    let Health::Broken { problems } = health else {
+        return Ok(());
+    };
+
+    if !force {
+        return Err(Error::Broken {
+            name: env.name.clone(),
+            path: env.path.clone(),
+            problems,
+        });
+    }
+    for problem in &problems {
+        print_warning(
+            &format!(
+                "Activating broken environment \"{}\": {}",
+                env.name, problem
+            ),
+            opts.no_color,
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,339 @@
+//! Directory fingerprints that let `--scan` skip unchanged directories
+//!
+//! Scanning the home folder lists every directory in it. Each listing is
+//! recorded next to the cache together with the directory's modification
+//! time, which changes whenever an entry is added to, removed from or renamed
+//! in that directory. The next scan only stats each directory and reuses the
+//! recorded listing while that time is unchanged, so only changed
+//! directories are read again. A directory modified within two seconds of a
+//! scan is read again by the next one regardless, since a coarse timestamp
+//! could hide a second change. Edits to files inside an environment need no
+//! listing: every environment found is detected afresh anyway.
+//!
+//! Subtrees are walked in parallel, and the walk stops at each environment's
+//! root instead of listing its `site-packages`.
+
+use crate::cache::write_atomic;
+use crate::config::SearchDir;
+use crate::error::{Error, Result};
+use crate::exclude::Excludes;
+use rayon::ThreadPoolBuilder;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Entry names the home scan collects
+pub const MARKERS: &[&str] = &["pyvenv.cfg", "pyproject.toml", "conda-meta"];
+
+/// Markers of a directory that is itself an environment
+const ENV_ROOT_MARKERS: &[&str] = &["pyvenv.cfg", "conda-meta"];
+
+const WORKER_STACK_SIZE: usize = 8 * 1024 * 1024;
+
+/// Directories modified this close to the scan may change again within the
+/// same timestamp tick (2 s on FAT), so their listing is not trusted later
+const RACY_WINDOW_NS: u64 = 2_000_000_000;
+
+/// Fingerprint and listing of one directory
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DirRecord {
+    /// Modification time in nanoseconds since the Unix epoch; 0 makes the
+    /// next scan read the directory again
+    #[serde(default)]
+    pub mtime: u64,
+    /// Entries named like one of [`MARKERS`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub markers: Vec<String>,
+    /// Every subdirectory; excluded ones and those beyond the depth limit
+    /// are not descended into and keep an empty record
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub children: BTreeMap<String, DirRecord>,
+}
+
+/// The recorded listings below one scan root
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanIndex {
+    pub root: PathBuf,
+    /// Whether symlinked folders were listed as subdirectories
+    #[serde(default)]
+    pub follow_symlinks: bool,
+    pub tree: DirRecord,
+}
+
+/// How much of the tree a walk had to read
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WalkStats {
+    /// Directories listed from disk
+    pub read: usize,
+    /// Directories whose recorded listing was reused
+    pub reused: usize,
+}
+
+/// Result of [`walk`]
+pub struct Walk {
+    pub index: ScanIndex,
+    /// Paths of every marker entry found
+    pub found: Vec<PathBuf>,
+    /// Directories skipped, and why
+    pub skipped: Vec<(PathBuf, String)>,
+    pub stats: WalkStats,
+}
+
+/// Index file kept next to `cache_file`
+pub fn index_path(cache_file: &Path) -> PathBuf {
+    cache_file.with_extension("index.json")
+}
+
+/// The index of each root at `path`; a missing or unreadable file just means
+/// a full walk
+pub fn load_index(path: &Path) -> Vec<ScanIndex> {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_index(path: &Path, index: &[ScanIndex]) -> Result<()> {
+    let json = serde_json::to_string(index).map_err(|e| Error::cache(path, e))?;
+    write_atomic(path, &json).map_err(|e| Error::cache(path, e))
+}
+
+/// Walks `root` as deep as it allows on a pool of `threads` workers,
+/// skipping subdirectories `excludes` rejects, and reusing the listings of
+/// the matching index in `previous` for directories that did not change
+/// since. Environments are not descended into: nothing below their root is
+/// needed. `within` is the scan root `root` lies in (usually itself); ignore
+/// files above it are not read.
+pub fn walk(
+    root: &SearchDir,
+    within: &Path,
+    previous: &[ScanIndex],
+    excludes: &Excludes,
+    threads: usize,
+) -> Walk {
+    let walker = Walker {
+        started: nanos(SystemTime::now()).unwrap_or(u64::MAX),
+        excludes,
+        within,
+        depth: root.depth,
+        follow_symlinks: root.follow_symlinks,
+    };
+    let previous = previous
+        .iter()
+        .find(|p| p.root == root.path && p.follow_symlinks == root.follow_symlinks)
+        .map(|p| &p.tree);
+    let run = || walker.visit(&root.path, previous, 0, None);
+    // Each subtree is a task, so deep recursion can pile up on one worker
+    let visited = match ThreadPoolBuilder::new()
+        .num_threads(threads.max(1))
+        .stack_size(WORKER_STACK_SIZE)
+        .build()
+    {
+        Ok(pool) => pool.install(run),
+        Err(_) => run(),
+    }
+    .unwrap_or_default();
+
+    Walk {
+        index: ScanIndex {
+            root: root.path.clone(),
+            follow_symlinks: root.follow_symlinks,
+            tree: visited.record,
+        },
+        found: visited.found,
+        skipped: visited.skipped,
+        stats: visited.stats,
+    }
+}
+
+/// Workers for [`walk`]: `APE_SCAN_THREADS`, else one per CPU
+pub fn scan_threads() -> usize {
+    env::var("APE_SCAN_THREADS")
+        .ok()
+        .and_then(|value| value.trim().parse().ok())
+        .filter(|&n| n > 0)
+        .unwrap_or_else(|| thread::available_parallelism().map_or(1, |n| n.get()))
+}
+
+struct Walker<'a> {
+    /// When the walk started, in nanoseconds since the Unix epoch
+    started: u64,
+    excludes: &'a Excludes,
+    /// Where ignore files stop being read
+    within: &'a Path,
+    /// Levels below the root to descend to
+    depth: Option<usize>,
+    follow_symlinks: bool,
+}
+
+/// What walking one subtree produced
+#[derive(Default)]
+struct Visited {
+    record: DirRecord,
+    found: Vec<PathBuf>,
+    skipped: Vec<(PathBuf, String)>,
+    stats: WalkStats,
+}
+
+impl Walker<'_> {
+    fn visit(
+        &self,
+        dir: &Path,
+        previous: Option<&DirRecord>,
+        level: usize,
+        ancestors: Option<&Ancestor>,
+    ) -> Option<Visited> {
+        // Followed symlinks are fingerprinted by their target
+        let mtime = fs::metadata(dir)
+            .ok()?
+            .modified()
+            .ok()
+            .and_then(nanos)
+            .unwrap_or(0);
+
+        let mut stats = WalkStats::default();
+        let (mut record, subdirs) = match previous {
+            Some(prev) if prev.mtime != 0 && prev.mtime == mtime => {
+                stats.reused += 1;
+                let record = DirRecord {
+                    mtime,
+                    markers: prev.markers.clone(),
+                    children: BTreeMap::new(),
+                };
+                (record, prev.children.keys().cloned().collect())
+            }
+            _ => {
+                stats.read += 1;
+                read(dir, mtime, self.follow_symlinks)
+            }
+        };
+        if record.mtime.saturating_add(RACY_WINDOW_NS) >= self.started {
+            record.mtime = 0;
+        }
+        let mut found: Vec<PathBuf> = record.markers.iter().map(|m| dir.join(m)).collect();
+        let mut skipped = Vec::new();
+        let here = if self.follow_symlinks {
+            fs::canonicalize(dir).ok().map(|path| Ancestor {
+                path,
+                parent: ancestors,
+            })
+        } else {
+            None
+        };
+
+        let children: Vec<(String, Option<Visited>)> = subdirs
+            .into_par_iter()
+            .map(|name| {
+                let path = dir.join(&name);
+                let child = if let Some(reason) = self.excludes.reason(&path, self.within) {
+                    Some(Visited {
+                        skipped: vec![(path, reason)],
+                        ..Visited::default()
+                    })
+                } else if self.depth.is_some_and(|depth| level >= depth) {
+                    Some(Visited::default())
+                } else if self.follow_symlinks && is_loop(&path, here.as_ref()) {
+                    None
+                } else {
+                    let previous = previous.and_then(|p| p.children.get(&name));
+                    self.visit(&path, previous, level + 1, here.as_ref())
+                };
+                (name, child)
+            })
+            .collect();
+        for (name, child) in children {
+            let Some(child) = child else {
+                continue;
+            };
+            found.extend(child.found);
+            skipped.extend(child.skipped);
+            stats.read += child.stats.read;
+            stats.reused += child.stats.reused;
+            record.children.insert(name, child.record);
+        }
+
+        Some(Visited {
+            record,
+            found,
+            skipped,
+            stats,
+        })
+    }
+}
+
+/// Lists `dir`: its marker entries and the names of its subdirectories,
+/// counting symlinks to directories only if `follow_symlinks`. An unreadable
+/// directory gets no timestamp, so it is tried again next time.
+fn read(dir: &Path, mtime: u64, follow_symlinks: bool) -> (DirRecord, Vec<String>) {
+    let mut record = DirRecord {
+        mtime,
+        ..DirRecord::default()
+    };
+    let mut subdirs = Vec::new();
+
+    let Ok(entries) = fs::read_dir(dir) else {
+        record.mtime = 0;
+        return (record, subdirs);
+    };
+    for entry in entries.filter_map(|e| e.ok()) {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if MARKERS.contains(&name.as_str()) {
+            record.markers.push(name.clone());
+        }
+        let is_dir = entry.file_type().is_ok_and(|t| {
+            t.is_dir() || (follow_symlinks && t.is_symlink() && entry.path().is_dir())
+        });
+        if is_dir {
+            subdirs.push(name);
+        }
+    }
+    record.markers.sort();
+
+    // An environment root: its site-packages alone can hold thousands of
+    // directories and no further environments
+    if record
+        .markers
+        .iter()
+        .any(|m| ENV_ROOT_MARKERS.contains(&m.as_str()))
+    {
+        subdirs.clear();
+    }
+
+    (record, subdirs)
+}
+
+/// Canonical path of a directory being walked, and of those above it, kept
+/// while following symlinks to notice loops
+struct Ancestor<'a> {
+    path: PathBuf,
+    parent: Option<&'a Ancestor<'a>>,
+}
+
+/// Whether `path` is a symlink to a directory the walk is already in (or
+/// one that cannot be resolved)
+fn is_loop(path: &Path, ancestors: Option<&Ancestor>) -> bool {
+    if !fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_symlink()) {
+        return false;
+    }
+    let Ok(target) = fs::canonicalize(path) else {
+        return true;
+    };
+    let mut ancestor = ancestors;
+    while let Some(a) = ancestor {
+        if a.path == target {
+            return true;
+        }
+        ancestor = a.parent;
+    }
+    false
+}
+
+fn nanos(time: SystemTime) -> Option<u64> {
+    let duration = time.duration_since(UNIX_EPOCH).ok()?;
+    u64::try_from(duration.as_nanos()).ok()
+}
@@ -0,0 +1,65 @@
+//! Shared core of APE and SPE: finding Python virtual environments, caching
+//! them, reading the user config and activating them through a shell wrapper.
+//!
+//! The `venv` binary (also installed as `ape-core` and `spe-core`) is a thin
+//! front-end over this crate; other tools can depend on it directly:
+//!
+//! ```no_run
+//! use venv_core::{cache, config, discovery, Options};
+//!
+//! let opts = Options::default();
+//! let cache_file = cache::get_cache_path()?;
+//! let dirs = config::get_search_dirs()?;
+//! let env = discovery::find_environment("finance", &cache_file, &dirs, &opts)?;
+//! println!("{} ({}) at {}", env.name, env.env_type, env.path.display());
+//! # Ok::<(), venv_core::Error>(())
+//! ```
+//!
+//! - [`environment`]: what an environment is and how one is recognized on disk
+//! - [`discovery`]: searching directories and scanning the home folder
+//! - [`doctor`]: auditing the cached environments for problems
+//! - [`cache`]: the persistent JSON cache of discovered environments
+//! - [`index`]: directory fingerprints that keep rescans incremental
+//! - [`conda`]: conda, mamba and micromamba environments and installations
+//! - [`config`]: `python_venv_config.toml` and the directories to search
+//! - [`exclude`]: the folders searches and scans skip
+//! - [`activation`]: handing an environment to the calling shell wrapper
+//! - [`error`]: the [`Error`] every operation returns, with its exit code
+//! - [`health`]: checking that an environment can still run Python
+//! - [`managers`]: Poetry, Pipenv, PDM, Hatch, Rye and pixi environments
+//! - [`matching`]: prefix, substring, subsequence and typo matching of names
+//! - [`project`]: the environment of the project a directory belongs to
+//! - [`pyvenv`]: typed metadata parsed from `pyvenv.cfg`
+//! - [`output`]: colored messages shared by all front-ends
+//! - [`watch`]: keeping the cache current as environments come and go
+
+pub mod activation;
+pub mod cache;
+pub mod conda;
+pub mod config;
+pub mod discovery;
+pub mod doctor;
+pub mod environment;
+pub mod error;
+pub mod exclude;
+pub mod health;
+pub mod index;
+pub mod managers;
+pub mod matching;
+pub mod output;
+pub mod project;
+pub mod pyvenv;
+pub mod watch;
+
+pub use activation::{ActivationPlan, Shell};
+pub use environment::{Environment, Layout};
+pub use error::{Error, Result};
+
+/// Output settings passed to every operation that reports progress
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Options {
+    /// Print `[DEBUG]` lines describing what is being searched and why
+    pub verbose: bool,
+    /// Print plain text instead of ANSI colors
+    pub no_color: bool,
+}
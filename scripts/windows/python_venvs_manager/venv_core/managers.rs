@@ -0,0 +1,313 @@
+//! Environments created by project managers: Poetry, Pipenv, PDM, Hatch,
+//! Rye and pixi
+//!
+//! Poetry, Pipenv, PDM and Hatch can keep environments in a central
+//! directory of their own (often below a `cache` folder the home scan
+//! skips), so those directories are searched explicitly. Rye and pixi, and
+//! the others when configured to, put the environment inside the project.
+
+use crate::config::{env_path, home_dir};
+use crate::environment::{Environment, is_generic_dir_name};
+use crate::project::pyproject_name;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A tool that creates and owns environments for a project
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Manager {
+    Poetry,
+    Pipenv,
+    Pdm,
+    Hatch,
+    Rye,
+    Pixi,
+}
+
+impl Manager {
+    /// Name recorded as the environment's `env_type`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Manager::Poetry => "poetry",
+            Manager::Pipenv => "pipenv",
+            Manager::Pdm => "pdm",
+            Manager::Hatch => "hatch",
+            Manager::Rye => "rye",
+            Manager::Pixi => "pixi",
+        }
+    }
+
+    /// How many levels below its central directory an environment sits
+    pub fn central_depth(self) -> usize {
+        match self {
+            // env/virtual/<project>/<hash>/<env>
+            Manager::Hatch => 3,
+            _ => 1,
+        }
+    }
+}
+
+/// What the manager of an environment tells about it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Managed {
+    pub manager: Manager,
+    /// Project directory, when the manager records it or it contains the env
+    pub project: Option<PathBuf>,
+    /// Name to activate it by, when the directory name is not it (e.g.
+    /// `billing` for Poetry's `billing-AbCd1234-py3.12`)
+    pub name: Option<String>,
+}
+
+/// Central environment directories of each manager that exist, honoring
+/// `POETRY_VIRTUALENVS_PATH`, `POETRY_CACHE_DIR`, `WORKON_HOME` and
+/// `HATCH_DATA_DIR`
+pub fn central_env_dirs() -> Vec<(Manager, PathBuf)> {
+    let mut dirs = Vec::new();
+
+    let poetry = env_path("POETRY_VIRTUALENVS_PATH").or_else(|| {
+        env_path("POETRY_CACHE_DIR")
+            .or_else(|| platform_cache_dir("pypoetry"))
+            .map(|dir| dir.join("virtualenvs"))
+    });
+    dirs.extend(poetry.map(|dir| (Manager::Poetry, dir)));
+
+    let pipenv = env_path("WORKON_HOME").or_else(|| {
+        if cfg!(windows) {
+            home_dir().map(|home| home.join(".virtualenvs"))
+        } else {
+            xdg_data_home().map(|dir| dir.join("virtualenvs"))
+        }
+    });
+    dirs.extend(pipenv.map(|dir| (Manager::Pipenv, dir)));
+
+    let pdm = platform_data_dir("pdm").map(|dir| {
+        // platformdirs nests the app name twice on Windows
+        if cfg!(windows) {
+            dir.join("pdm").join("venvs")
+        } else {
+            dir.join("venvs")
+        }
+    });
+    dirs.extend(pdm.map(|dir| (Manager::Pdm, dir)));
+
+    let hatch = env_path("HATCH_DATA_DIR").or_else(|| platform_data_dir("hatch"));
+    dirs.extend(hatch.map(|dir| (Manager::Hatch, dir.join("env").join("virtual"))));
+
+    dirs.retain(|(_, dir)| dir.is_dir());
+    dirs
+}
+
+/// `~/.local/share`, or `XDG_DATA_HOME`
+fn xdg_data_home() -> Option<PathBuf> {
+    env_path("XDG_DATA_HOME")
+        .filter(|p| p.is_absolute())
+        .or_else(|| home_dir().map(|home| home.join(".local").join("share")))
+}
+
+/// Where `app` keeps its data: `%LOCALAPPDATA%\<app>`, macOS Application
+/// Support or the XDG data dir
+fn platform_data_dir(app: &str) -> Option<PathBuf> {
+    if cfg!(windows) {
+        env_path("LOCALAPPDATA").map(|dir| dir.join(app))
+    } else if cfg!(target_os = "macos") {
+        home_dir().map(|home| home.join("Library").join("Application Support").join(app))
+    } else {
+        xdg_data_home().map(|dir| dir.join(app))
+    }
+}
+
+/// Where `app` keeps its caches: `%LOCALAPPDATA%\<app>\Cache`, macOS
+/// `Library/Caches` or the XDG cache dir
+fn platform_cache_dir(app: &str) -> Option<PathBuf> {
+    if cfg!(windows) {
+        env_path("LOCALAPPDATA").map(|dir| dir.join(app).join("Cache"))
+    } else if cfg!(target_os = "macos") {
+        home_dir().map(|home| home.join("Library").join("Caches").join(app))
+    } else {
+        env_path("XDG_CACHE_HOME")
+            .filter(|p| p.is_absolute())
+            .or_else(|| home_dir().map(|home| home.join(".cache")))
+            .map(|dir| dir.join(app))
+    }
+}
+
+/// The manager owning the environment at `path`, if one can be told: from
+/// where it lives, the marker files it leaves in the environment, or the
+/// lock file of the project holding a `.venv`
+pub fn detect_manager(path: &Path) -> Option<Managed> {
+    let dir = file_name(path);
+
+    // <project>/.pixi/envs/<env>
+    if let Some(envs) = path.parent()
+        && file_name(envs) == "envs"
+        && let Some(pixi) = envs.parent()
+        && file_name(pixi) == ".pixi"
+    {
+        let project = pixi.parent().map(Path::to_path_buf);
+        let name = project
+            .as_deref()
+            .map(|p| env_name_in_project(&file_name(p), &dir));
+        return Some(Managed {
+            manager: Manager::Pixi,
+            project,
+            name,
+        });
+    }
+
+    if path.join("rye-venv.json").is_file() {
+        return Some(in_project(Manager::Rye, path));
+    }
+
+    // Pipenv records the project it was created for, but virtualenvwrapper
+    // (whose $WORKON_HOME Pipenv shares) writes the same file; the latter's
+    // environments stay plain venvs linked to their project
+    if let Some(project) = linked_project(path) {
+        if !project.join("Pipfile").is_file() {
+            return None;
+        }
+        return Some(Managed {
+            manager: Manager::Pipenv,
+            name: project.file_name().map(|n| n.to_string_lossy().to_string()),
+            project: Some(project),
+        });
+    }
+
+    for (manager, central) in central_env_dirs() {
+        let Ok(relative) = path.strip_prefix(&central) else {
+            continue;
+        };
+        let parts: Vec<String> = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().to_string())
+            .collect();
+        if parts.len() != manager.central_depth() {
+            continue;
+        }
+        let name = match manager {
+            Manager::Poetry => poetry_project_name(&dir),
+            Manager::Pdm => pdm_project_name(&dir),
+            Manager::Pipenv => strip_hash(&dir, 8),
+            Manager::Hatch => Some(env_name_in_project(&parts[0], &dir)),
+            Manager::Rye | Manager::Pixi => None,
+        };
+        return Some(Managed {
+            manager,
+            project: None,
+            name,
+        });
+    }
+
+    // An in-project environment of a project the lock file names a tool for
+    if is_generic_dir_name(&dir)
+        && let Some(project) = path.parent()
+    {
+        let has = |file: &str| project.join(file).is_file();
+        let manager = if has("poetry.lock") {
+            Some(Manager::Poetry)
+        } else if has("pdm.lock") || has(".pdm-python") {
+            Some(Manager::Pdm)
+        } else if has("Pipfile") {
+            Some(Manager::Pipenv)
+        } else {
+            None
+        };
+        return manager.map(|m| in_project(m, path));
+    }
+
+    None
+}
+
+/// Project named by the `.project` file Pipenv and virtualenvwrapper leave
+/// in an environment
+pub fn linked_project(path: &Path) -> Option<PathBuf> {
+    let contents = fs::read_to_string(path.join(".project")).ok()?;
+    let project = contents.trim();
+    (!project.is_empty()).then(|| PathBuf::from(project))
+}
+
+fn in_project(manager: Manager, path: &Path) -> Managed {
+    Managed {
+        manager,
+        project: path.parent().map(Path::to_path_buf),
+        name: None,
+    }
+}
+
+/// `project` for a project's default environment, else `project-env`
+fn env_name_in_project(project: &str, env: &str) -> String {
+    if env == "default" || env.eq_ignore_ascii_case(project) {
+        project.to_string()
+    } else {
+        format!("{}-{}", project, env)
+    }
+}
+
+/// `billing` from `billing-AbCd1234-py3.12`
+fn poetry_project_name(dir: &str) -> Option<String> {
+    let (rest, python) = dir.rsplit_once('-')?;
+    if !python.starts_with("py") {
+        return None;
+    }
+    strip_hash(rest, 8)
+}
+
+/// `billing` from `billing-AbCd1234-3.12`
+fn pdm_project_name(dir: &str) -> Option<String> {
+    let (rest, _) = dir.rsplit_once('-')?;
+    strip_hash(rest, 8)
+}
+
+/// `name` from `name-<hash>` where the hash is `len` characters long; the
+/// base64 alphabet used for it may itself contain `-`
+fn strip_hash(dir: &str, len: usize) -> Option<String> {
+    let split = dir.len().checked_sub(len + 1)?;
+    let (name, hash) = dir.split_at_checked(split)?;
+    (hash.starts_with('-') && !name.is_empty()).then(|| name.to_string())
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// Fills in the project directory of centrally stored environments whose
+/// manager only records the project's name, choosing among `projects`
+/// (directories holding a `pyproject.toml`). Names shared by several
+/// projects are left unlinked.
+pub fn link_projects(environments: &mut [Environment], projects: &[PathBuf]) {
+    let names: Vec<(String, &PathBuf)> = projects
+        .iter()
+        .flat_map(|dir| {
+            let mut names = vec![normalize(&file_name(dir))];
+            names.extend(pyproject_name(dir).map(|n| normalize(&n)));
+            names.dedup();
+            names.into_iter().map(move |n| (n, dir))
+        })
+        .collect();
+
+    for env in environments.iter_mut().filter(|e| e.project.is_none()) {
+        let wanted = match env.env_type.as_str() {
+            "poetry" | "pdm" => normalize(&env.name),
+            // env/virtual/<project>/<hash>/<env>
+            "hatch" => match env.path.parent().and_then(Path::parent) {
+                Some(project) => normalize(&file_name(project)),
+                None => continue,
+            },
+            _ => continue,
+        };
+        let mut owners: Vec<&PathBuf> = names
+            .iter()
+            .filter(|(name, _)| *name == wanted)
+            .map(|(_, dir)| *dir)
+            .collect();
+        owners.dedup();
+        if let [owner] = owners[..] {
+            env.project = Some(owner.clone());
+        }
+    }
+}
+
+/// Lowercase with `_`, `.` and spaces as `-`, as package names compare
+fn normalize(name: &str) -> String {
+    name.to_lowercase().replace(['_', '.', ' '], "-")
+}
@@ -0,0 +1,79 @@
+//! Loose name matching: prefixes, substrings, subsequences and typos
+
+/// How loosely `query` matches `name` (case-insensitive): 0 for a prefix,
+/// 1 for a substring, 2 for a subsequence (`fnc` in `finance`). Queries
+/// shorter than two characters only match as a prefix.
+pub fn fuzzy_rank(name: &str, query: &str) -> Option<u8> {
+    let name = name.to_lowercase();
+    let query = query.to_lowercase();
+    if query.is_empty() {
+        return None;
+    }
+    if name.starts_with(&query) {
+        return Some(0);
+    }
+    if query.chars().count() < 2 {
+        return None;
+    }
+    if name.contains(&query) {
+        return Some(1);
+    }
+    let mut chars = name.chars();
+    if query.chars().all(|q| chars.any(|c| c == q)) {
+        return Some(2);
+    }
+    None
+}
+
+/// Levenshtein distance between `a` and `b`, ignoring case
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.to_lowercase().chars().collect();
+    let b: Vec<char> = b.to_lowercase().chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Most edits a name may be away from `query` to be suggested
+fn max_typos(query: &str) -> usize {
+    match query.chars().count() {
+        0..=3 => 1,
+        4..=7 => 2,
+        _ => 3,
+    }
+}
+
+/// Up to `limit` of `names` that look like a misspelling of `query`,
+/// closest first
+pub fn suggestions<'a>(
+    query: &str,
+    names: impl IntoIterator<Item = &'a str>,
+    limit: usize,
+) -> Vec<String> {
+    let max = max_typos(query);
+    let mut scored: Vec<(usize, &str)> = names
+        .into_iter()
+        .map(|name| (edit_distance(name, query), name))
+        .filter(|(distance, _)| *distance <= max)
+        .collect();
+    scored.sort();
+    scored.dedup_by(|a, b| a.1.eq_ignore_ascii_case(b.1));
+    scored
+        .into_iter()
+        .take(limit)
+        .map(|(_, name)| name.to_string())
+        .collect()
+}
+
+/// Whether a directory named `name` could be a typo of `query`
+pub fn is_close(name: &str, query: &str) -> bool {
+    edit_distance(name, query) <= max_typos(query)
+}
@@ -0,0 +1,81 @@
+//! Colored status messages and the stdout routing used by `--shell`
+
+use colored::*;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Set with `--shell`: stdout then carries only code for the wrapper to eval
+static STDOUT_RESERVED: AtomicBool = AtomicBool::new(false);
+
+/// Reserves stdout for shell code; every message printed through this module
+/// or [`outln!`](crate::outln) goes to stderr from then on
+pub fn reserve_stdout() {
+    STDOUT_RESERVED.store(true, Ordering::Relaxed);
+}
+
+pub fn stdout_reserved() -> bool {
+    STDOUT_RESERVED.load(Ordering::Relaxed)
+}
+
+/// `println!` that moves to stderr while stdout is reserved for shell code
+#[macro_export]
+macro_rules! outln {
+    ($($arg:tt)*) => {
+        if $crate::output::stdout_reserved() {
+            eprintln!($($arg)*)
+        } else {
+            println!($($arg)*)
+        }
+    };
+}
+
+/// Prints an inline prompt (no newline) on whichever stream messages use
+pub fn prompt(text: &str) {
+    if stdout_reserved() {
+        eprint!("{}", text);
+        let _ = io::stderr().flush();
+    } else {
+        print!("{}", text);
+        let _ = io::stdout().flush();
+    }
+}
+
+pub fn print_error(msg: &str, no_color: bool) {
+    if no_color {
+        eprintln!("Error: {}", msg);
+    } else {
+        eprintln!("{} {}", "Error:".red().bold(), msg);
+    }
+}
+
+pub fn print_warning(msg: &str, no_color: bool) {
+    if no_color {
+        eprintln!("Warning: {}", msg);
+    } else {
+        eprintln!("{} {}", "Warning:".yellow().bold(), msg);
+    }
+}
+
+pub fn print_success(msg: &str, no_color: bool) {
+    if no_color {
+        outln!("{}", msg);
+    } else {
+        outln!("{}", msg.green());
+    }
+}
+
+pub fn print_info(msg: &str, no_color: bool) {
+    if no_color {
+        outln!("{}", msg);
+    } else {
+        outln!("{}", msg.cyan());
+    }
+}
+
+pub fn print_debug(msg: &str, no_color: bool) {
+    if no_color {
+        outln!("[DEBUG] {}", msg);
+    } else {
+        outln!("{}", format!("[DEBUG] {}", msg).dimmed());
+    }
+}
@@ -0,0 +1,205 @@
+//! Finding the environment of the project a directory belongs to
+//!
+//! `ape` with no name (or `ape .`) walks up from the current directory, no
+//! further than the home folder, and stops at the first directory that is
+//! an environment, pins one, holds a
+//! `.venv`/`venv`/`env` folder, owns a cached environment of Poetry, Pipenv,
+//! PDM, Hatch or pixi, or has a `pyproject.toml` whose environment is in the
+//! cache.
+
+use crate::Options;
+use crate::cache::{load_cache, update_cache};
+use crate::config::home_dir;
+use crate::environment::{Environment, FUZZY_RANK, GENERIC_DIR_NAMES, detect_environment_at_path};
+use crate::error::{Error, Result};
+use crate::output::{print_debug, print_warning};
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Files naming the environment of the directory they are in, in order of
+/// preference; the first line holds the name (or a path relative to the file)
+pub const PIN_FILES: &[&str] = &[".python-venv", ".python-version"];
+
+/// The environment of the project `start` is in, searching `start` and then
+/// each of its ancestors up to the home folder (or the filesystem root
+/// outside it)
+pub fn find_project_environment(
+    start: &Path,
+    cache_file: &Path,
+    opts: &Options,
+) -> Result<Environment> {
+    let cached = load_valid_cache(cache_file, opts);
+    let home = home_dir();
+
+    for dir in start.ancestors() {
+        if opts.verbose {
+            print_debug(&format!("Looking in {}", dir.display()), opts.no_color);
+        }
+        if let Some(env) = environment_in(dir, &cached, opts) {
+            return Ok(env);
+        }
+        if home.as_deref() == Some(dir) {
+            break;
+        }
+    }
+
+    Err(Error::NotFound {
+        name: start
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| ".".to_string()),
+        suggestions: Vec::new(),
+    })
+}
+
+/// Cached environments that still exist, dropping the others from the
+/// cache; an unreadable cache counts as empty
+fn load_valid_cache(cache_file: &Path, opts: &Options) -> Vec<Environment> {
+    if !cache_file.exists() {
+        return Vec::new();
+    }
+    match load_cache(cache_file, opts) {
+        Ok(environments) => {
+            let (valid, gone): (Vec<Environment>, Vec<Environment>) =
+                environments.into_iter().partition(|e| e.exists());
+            let gone: Vec<PathBuf> = gone.into_iter().map(|e| e.path).collect();
+            update_cache(cache_file, &gone, &[], opts);
+            valid
+        }
+        Err(e) => {
+            print_warning(&format!("Ignoring cache: {}", e), opts.no_color);
+            Vec::new()
+        }
+    }
+}
+
+/// The environment `dir` itself provides, if any
+fn environment_in(dir: &Path, cached: &[Environment], opts: &Options) -> Option<Environment> {
+    let found = |how: &str, env: Environment| {
+        if opts.verbose {
+            print_debug(
+                &format!("Found {} at {} ({})", env.name, env.path.display(), how),
+                opts.no_color,
+            );
+        }
+        Some(env)
+    };
+
+    // Standing inside the environment itself
+    if let Some(env) = marked_environment_at(dir) {
+        return found("current environment", env);
+    }
+
+    for pin in PIN_FILES {
+        let Some(name) = read_pin(&dir.join(pin)) else {
+            continue;
+        };
+        if let Some(env) = marked_environment_at(&dir.join(&name)) {
+            return found(pin, env);
+        }
+        // Only exact names: a pinned Python version must not fuzzy-match
+        if let Some(env) = cached
+            .iter()
+            .find(|e| e.match_rank(&name).is_some_and(|r| r < FUZZY_RANK))
+        {
+            return found(pin, env.clone());
+        }
+    }
+
+    for name in GENERIC_DIR_NAMES {
+        if let Some(env) = marked_environment_at(&dir.join(name)) {
+            return found("project folder", env);
+        }
+    }
+
+    // A managed environment kept outside the project (Poetry, Pipenv, ...),
+    // preferring the default one named after the project
+    let owned: Vec<&Environment> = cached
+        .iter()
+        .filter(|e| e.project.as_deref() == Some(dir))
+        .collect();
+    if let Some(env) = owned
+        .iter()
+        .find(|e| {
+            dir.file_name()
+                .is_some_and(|d| e.name.eq_ignore_ascii_case(&d.to_string_lossy()))
+        })
+        .or(owned.first())
+    {
+        let how = format!("{} project", env.env_type);
+        return found(&how, (*env).clone());
+    }
+
+    if dir.join("pyproject.toml").is_file() {
+        // An environment inside the project that the walk above did not name
+        if let Some(env) = cached.iter().find(|e| e.path.parent() == Some(dir)) {
+            return found("pyproject.toml", env.clone());
+        }
+        if let Some(project) = pyproject_name(dir)
+            && let Some(env) = cached.iter().find(|e| names_project(e, &project))
+        {
+            return found("pyproject.toml", env.clone());
+        }
+    }
+
+    None
+}
+
+/// The environment at `path` if it has a venv or conda marker, so a system
+/// prefix such as /usr is never taken for a project's environment
+fn marked_environment_at(path: &Path) -> Option<Environment> {
+    if !path.join("pyvenv.cfg").is_file() && !path.join("conda-meta").is_dir() {
+        return None;
+    }
+    detect_environment_at_path(path)
+}
+
+/// First non-empty, non-comment line of a pin file
+fn read_pin(path: &Path) -> Option<String> {
+    let contents = fs::read_to_string(path).ok()?;
+    contents
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+}
+
+#[derive(Deserialize)]
+struct PyProject {
+    project: Option<ProjectTable>,
+    tool: Option<ToolTable>,
+}
+
+#[derive(Deserialize)]
+struct ToolTable {
+    poetry: Option<ProjectTable>,
+}
+
+#[derive(Deserialize)]
+struct ProjectTable {
+    name: Option<String>,
+}
+
+/// `[project].name`, else `[tool.poetry].name`
+pub fn pyproject_name(dir: &Path) -> Option<String> {
+    let contents = fs::read_to_string(dir.join("pyproject.toml")).ok()?;
+    let pyproject: PyProject = toml::from_str(&contents).ok()?;
+    pyproject
+        .project
+        .and_then(|p| p.name)
+        .or_else(|| pyproject.tool.and_then(|t| t.poetry).and_then(|p| p.name))
+}
+
+/// Whether `env` belongs to the project called `project`: same name, or a
+/// Poetry-style `<project>-<hash>-py3.X` directory
+fn names_project(env: &Environment, project: &str) -> bool {
+    let normalized = project.to_lowercase().replace(['_', '.'], "-");
+    let dir = env
+        .path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    env.name.to_lowercase().replace(['_', '.'], "-") == normalized
+        || dir.starts_with(&format!("{}-", normalized))
+}
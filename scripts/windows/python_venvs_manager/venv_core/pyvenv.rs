@@ -0,0 +1,160 @@
+//! Parsing `pyvenv.cfg` into typed metadata
+//!
+//! The file is a flat list of `key = value` lines. Which keys appear depends
+//! on the tool that created the environment: stdlib `venv` writes `version`,
+//! virtualenv and uv write `version_info` and `implementation` plus their
+//! own name as a key holding their version.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// What `pyvenv.cfg` says about an environment
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PyvenvConfig {
+    /// Directory of the base interpreter
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub home: Option<PathBuf>,
+    /// Python version, e.g. `3.12.1` (from `version` or `version_info`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// `CPython`, `PyPy`, ...
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub implementation: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub include_system_site_packages: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub creator: Option<Creator>,
+}
+
+/// Tool that created the environment
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Creator {
+    pub kind: CreatorKind,
+    /// Version of the tool; the stdlib `venv` does not record one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CreatorKind {
+    Uv,
+    Virtualenv,
+    /// `python -m venv`
+    Venv,
+}
+
+impl fmt::Display for Creator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self.kind {
+            CreatorKind::Uv => "uv",
+            CreatorKind::Virtualenv => "virtualenv",
+            CreatorKind::Venv => "venv",
+        };
+        match &self.version {
+            Some(version) => write!(f, "{} {}", name, version),
+            None => write!(f, "{}", name),
+        }
+    }
+}
+
+impl PyvenvConfig {
+    /// `3.12.1`, or `PyPy 3.10.14` for implementations other than CPython
+    pub fn python_label(&self) -> Option<String> {
+        let version = self.version.as_deref()?;
+        match self.implementation.as_deref() {
+            Some(imp) if !imp.eq_ignore_ascii_case("cpython") => {
+                Some(format!("{} {}", imp, version))
+            }
+            _ => Some(version.to_string()),
+        }
+    }
+}
+
+/// Reads `pyvenv.cfg` in the environment at `env_path`, if there is one
+pub fn read_pyvenv_cfg(env_path: &Path) -> Option<PyvenvConfig> {
+    let contents = fs::read_to_string(env_path.join("pyvenv.cfg")).ok()?;
+    Some(parse_pyvenv_cfg(&contents))
+}
+
+/// Parses the contents of a `pyvenv.cfg`; unknown keys and malformed lines
+/// are ignored
+pub fn parse_pyvenv_cfg(contents: &str) -> PyvenvConfig {
+    let mut config = PyvenvConfig::default();
+    let mut version_info = None;
+    let mut creator = None;
+
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let key = key.trim().to_ascii_lowercase().replace('_', "-");
+        let value = value.trim();
+        if value.is_empty() {
+            continue;
+        }
+
+        match key.as_str() {
+            "home" => config.home = Some(PathBuf::from(value)),
+            "version" => config.version = Some(short_version(value)),
+            "version-info" => version_info = Some(short_version(value)),
+            "implementation" => config.implementation = Some(value.to_string()),
+            "include-system-site-packages" => {
+                config.include_system_site_packages = match value.to_ascii_lowercase().as_str() {
+                    "true" => Some(true),
+                    "false" => Some(false),
+                    _ => None,
+                }
+            }
+            // `python -m venv --prompt` writes the value quoted
+            "prompt" => {
+                let prompt = value.trim_matches(['\'', '"']).trim();
+                if !prompt.is_empty() {
+                    config.prompt = Some(prompt.to_string());
+                }
+            }
+            "uv" => {
+                creator = Some(Creator {
+                    kind: CreatorKind::Uv,
+                    version: Some(value.to_string()),
+                })
+            }
+            "virtualenv" if creator.is_none() => {
+                creator = Some(Creator {
+                    kind: CreatorKind::Virtualenv,
+                    version: Some(value.to_string()),
+                })
+            }
+            _ => {}
+        }
+    }
+
+    config.version = config.version.or(version_info);
+    config.creator = creator.or(Some(Creator {
+        kind: CreatorKind::Venv,
+        version: None,
+    }));
+    config
+}
+
+/// `3.12.1.final.0` -> `3.12.1`
+fn short_version(value: &str) -> String {
+    let parts: Vec<&str> = value
+        .split('.')
+        .take_while(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()))
+        .take(3)
+        .collect();
+    if parts.is_empty() {
+        value.to_string()
+    } else {
+        parts.join(".")
+    }
+}
@@ -0,0 +1,494 @@
+//! `venv watch`: keeping the cache current while environments come and go
+//!
+//! After an incremental scan brings the cache up to date, every folder that
+//! scan walked is watched for entries appearing and disappearing (inotify on
+//! Linux, ReadDirectoryChangesW on Windows, FSEvents on macOS). Folders are
+//! watched one at a time rather than as whole trees, so excluded folders and
+//! the insides of environments, where most directories are, cost no
+//! watches; folders created later are searched and watched as they appear.
+//! Folders reached through symlinks are not watched: the folder they point
+//! to can only be watched under one of its paths.
+
+use crate::Options;
+use crate::cache::{lock_cache, merge_into_cache, rescan};
+use crate::config::{SearchDir, describe, get_scan_roots};
+use crate::discovery::is_pixi_env;
+use crate::environment::{Environment, detect_environment_at_path};
+use crate::error::{Error, Result};
+use crate::exclude::Excludes;
+use crate::index::{DirRecord, index_path, load_index, walk};
+use crate::output::{print_debug, print_error, print_info, print_success, print_warning};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::{BTreeSet, HashMap};
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+/// How often the stop flag is checked while nothing happens
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Events this close together are handled as one change, so a venv being
+/// created is looked at once its `pyvenv.cfg` is written
+const SETTLE_TIME: Duration = Duration::from_millis(500);
+
+/// Longest a burst of events is collected before it is handled
+const MAX_BATCH_TIME: Duration = Duration::from_secs(5);
+
+/// Pid file of the watcher, next to `cache_file`
+pub fn pid_path(cache_file: &Path) -> PathBuf {
+    cache_file.with_extension("pid")
+}
+
+/// The pid file of the running watcher, removed when dropped. It stays
+/// locked while the watcher runs, so one left behind by a crashed watcher
+/// does not keep the next from starting.
+#[derive(Debug)]
+pub struct PidFile {
+    path: PathBuf,
+    _file: File,
+}
+
+impl PidFile {
+    /// Writes this process's id to `path`, unless another watcher holds it
+    pub fn create(path: &Path) -> Result<PidFile> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .write(true)
+            .truncate(false)
+            .open(path)?;
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                // Windows does not let other processes read a locked file
+                let mut pid = String::new();
+                let _ = file.read_to_string(&mut pid);
+                let pid = pid.trim();
+                let running = if pid.is_empty() {
+                    String::new()
+                } else {
+                    format!(" (pid {})", pid)
+                };
+                return Err(Error::Io(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!("venv watch is already running{}", running),
+                )));
+            }
+            Err(TryLockError::Error(e)) => return Err(e.into()),
+        }
+
+        file.set_len(0)?;
+        write!(file, "{}", process::id())?;
+        file.sync_all()?;
+        Ok(PidFile {
+            path: path.to_path_buf(),
+            _file: file,
+        })
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Scans, then keeps the cache up to date as environments are created or
+/// removed under the scan roots, until `stop` is set
+pub fn watch(cache_file: &Path, stop: &AtomicBool, opts: &Options) -> Result<()> {
+    let _pid_file = PidFile::create(&pid_path(cache_file))?;
+    // Watching still works from an older index and cache
+    if let Err(e) = rescan(cache_file, false, opts) {
+        print_error(&e.to_string(), opts.no_color);
+    }
+
+    let roots = get_scan_roots()?;
+    let excludes = Excludes::load()?;
+    let indexes = load_index(&index_path(cache_file));
+    let (tx, rx) = mpsc::channel();
+    let watcher = notify::recommended_watcher(tx).map_err(|e| Error::Io(io::Error::other(e)))?;
+    let mut watched = Watched {
+        watcher,
+        roots: roots.iter().map(|root| root.path.clone()).collect(),
+        dirs: HashMap::new(),
+        failed: 0,
+        first_error: None,
+    };
+    for root in &roots {
+        if let Some(index) = indexes.iter().find(|i| i.root == root.path) {
+            watched.add_tree(&root.path, &index.tree, root.depth, &root.path, &excludes);
+        }
+    }
+    watched.report_failures(opts);
+
+    print_info(
+        &format!(
+            "Watching {} folders for environments (Ctrl+C to stop):",
+            watched.dirs.len()
+        ),
+        opts.no_color,
+    );
+    for root in &roots {
+        crate::outln!("  {}", describe(root));
+    }
+    crate::outln!();
+
+    while !stop.load(Ordering::Relaxed) {
+        let first = match rx.recv_timeout(POLL_INTERVAL) {
+            Ok(event) => event,
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+
+        let mut changes = Changes::default();
+        changes.add(first, opts);
+        let deadline = Instant::now() + MAX_BATCH_TIME;
+        while Instant::now() < deadline
+            && let Ok(event) = rx.recv_timeout(SETTLE_TIME)
+        {
+            changes.add(event, opts);
+        }
+
+        let result = if changes.rescan {
+            // Events were lost, e.g. the kernel's queue overflowed
+            print_info("Missed some changes, rescanning...", opts.no_color);
+            rescan(cache_file, false, opts).map(drop)
+        } else {
+            apply(changes.paths, &mut watched, &excludes, cache_file, opts)
+        };
+        // A failed update (the cache removed by `venv clean`, a folder
+        // deleted while read) is reported; the next change may succeed
+        if let Err(e) = result {
+            print_error(&e.to_string(), opts.no_color);
+        }
+        watched.report_failures(opts);
+    }
+
+    print_info("Stopped watching.", opts.no_color);
+    Ok(())
+}
+
+/// The folders being watched
+struct Watched {
+    watcher: RecommendedWatcher,
+    /// The scan roots, which bound the ignore files read for folders below
+    roots: Vec<PathBuf>,
+    /// Each watched folder, with how many levels below it may be watched
+    /// (`None` for any)
+    dirs: HashMap<PathBuf, Option<usize>>,
+    /// Folders that could not be watched since the last report
+    failed: usize,
+    first_error: Option<String>,
+}
+
+impl Watched {
+    /// Watches `dir` and the folders below it that `record`, a walk of it,
+    /// descended into, except those reached through symlinks. `within` is
+    /// the scan root `dir` lies in
+    fn add_tree(
+        &mut self,
+        dir: &Path,
+        record: &DirRecord,
+        depth: Option<usize>,
+        within: &Path,
+        excludes: &Excludes,
+    ) {
+        self.add(dir, depth);
+        if depth == Some(0) {
+            return;
+        }
+        for (name, child) in &record.children {
+            let path = dir.join(name);
+            if excludes.reason(&path, within).is_none() && !is_symlink(&path) {
+                self.add_tree(&path, child, depth.map(|d| d - 1), within, excludes);
+            }
+        }
+    }
+
+    fn add(&mut self, dir: &Path, depth: Option<usize>) {
+        match self.watcher.watch(dir, RecursiveMode::NonRecursive) {
+            Ok(()) => {
+                self.dirs.insert(dir.to_path_buf(), depth);
+            }
+            Err(e) => {
+                self.failed += 1;
+                self.first_error.get_or_insert_with(|| e.to_string());
+            }
+        }
+    }
+
+    /// Forgets the watched folders at or below `path`, which is gone
+    fn remove_below(&mut self, path: &Path) {
+        let gone: Vec<PathBuf> = self
+            .dirs
+            .keys()
+            .filter(|dir| dir.starts_with(path))
+            .cloned()
+            .collect();
+        for dir in gone {
+            let _ = self.watcher.unwatch(&dir);
+            self.dirs.remove(&dir);
+        }
+    }
+
+    /// The innermost scan root holding `path`
+    fn root_of(&self, path: &Path) -> PathBuf {
+        self.roots
+            .iter()
+            .filter(|root| path.starts_with(root))
+            .max_by_key(|root| root.components().count())
+            .cloned()
+            .unwrap_or_else(|| path.to_path_buf())
+    }
+
+    /// How deep below the new folder `path` to watch, or `None` if it is
+    /// not to be watched: its parent is not watched, is as deep as allowed,
+    /// or is an environment
+    fn depth_for(&self, path: &Path) -> Option<Option<usize>> {
+        let parent = path.parent()?;
+        let depth = *self.dirs.get(parent)?;
+        if depth == Some(0) || is_environment_root(parent) {
+            return None;
+        }
+        Some(depth.map(|d| d - 1))
+    }
+
+    fn report_failures(&mut self, opts: &Options) {
+        if self.failed == 0 {
+            return;
+        }
+        let hint = if cfg!(target_os = "linux") {
+            "; raising fs.inotify.max_user_watches may help"
+        } else {
+            ""
+        };
+        print_warning(
+            &format!(
+                "Could not watch {} folders: {}{}",
+                self.failed,
+                self.first_error.take().unwrap_or_default(),
+                hint
+            ),
+            opts.no_color,
+        );
+        self.failed = 0;
+    }
+}
+
+/// What a burst of events touched
+#[derive(Default)]
+struct Changes {
+    paths: BTreeSet<PathBuf>,
+    /// Whether events were lost, so only a rescan can tell what changed
+    rescan: bool,
+}
+
+impl Changes {
+    fn add(&mut self, event: notify::Result<Event>, opts: &Options) {
+        let event = match event {
+            Ok(event) => event,
+            Err(e) => {
+                if opts.verbose {
+                    print_debug(&format!("Watch error: {}", e), opts.no_color);
+                }
+                return;
+            }
+        };
+        if event.need_rescan() {
+            self.rescan = true;
+        }
+        // Only entries appearing, disappearing or being renamed matter
+        match event.kind {
+            EventKind::Access(_)
+            | EventKind::Modify(notify::event::ModifyKind::Data(_))
+            | EventKind::Modify(notify::event::ModifyKind::Metadata(_)) => {}
+            _ => self.paths.extend(event.paths),
+        }
+    }
+}
+
+/// Updates the watches and the cache for the entries at `paths`, looking
+/// at what is on disk now rather than at what the events said
+fn apply(
+    paths: BTreeSet<PathBuf>,
+    watched: &mut Watched,
+    excludes: &Excludes,
+    cache_file: &Path,
+    opts: &Options,
+) -> Result<()> {
+    let mut gone: Vec<PathBuf> = Vec::new();
+    let mut env_dirs: BTreeSet<PathBuf> = BTreeSet::new();
+
+    for path in paths {
+        let Ok(metadata) = fs::symlink_metadata(&path) else {
+            watched.remove_below(&path);
+            if is_marker(&path)
+                && let Some(parent) = path.parent()
+            {
+                gone.push(parent.to_path_buf());
+            }
+            gone.push(path);
+            continue;
+        };
+
+        if is_marker(&path)
+            && let Some(parent) = path.parent()
+        {
+            env_dirs.insert(parent.to_path_buf());
+        }
+        let within = watched.root_of(&path);
+        if metadata.is_dir()
+            && !watched.dirs.contains_key(&path)
+            && excludes.reason(&path, &within).is_none()
+            && let Some(depth) = watched.depth_for(&path)
+        {
+            // A new folder, possibly with a whole tree in it already
+            let walked = walk(
+                &SearchDir {
+                    path: path.clone(),
+                    depth,
+                    follow_symlinks: false,
+                },
+                &within,
+                &[],
+                excludes,
+                1,
+            );
+            watched.add_tree(&path, &walked.index.tree, depth, &within, excludes);
+            env_dirs.extend(
+                walked
+                    .found
+                    .iter()
+                    .filter(|marker| is_marker(marker))
+                    .filter_map(|marker| marker.parent().map(Path::to_path_buf)),
+            );
+        }
+    }
+
+    // Folders inside an environment created before its marker (as `python
+    // -m venv` does) were watched as ordinary folders until now
+    for dir in &env_dirs {
+        if let Some(&depth) = watched.dirs.get(dir) {
+            watched.remove_below(dir);
+            watched.add(dir, depth);
+        }
+    }
+
+    let found: Vec<Environment> = env_dirs
+        .iter()
+        .filter_map(|dir| detect_environment_at_path(dir))
+        .collect();
+    if gone.is_empty() && found.is_empty() {
+        return Ok(());
+    }
+
+    let (removed, added) = {
+        let _lock = lock_cache(cache_file, opts)?;
+        merge_into_cache(cache_file, &gone, &found)?
+    };
+    for env in removed {
+        print_info(
+            &format!(
+                "Removed {} ({}) at {}",
+                env.name,
+                env.env_type,
+                env.path.display()
+            ),
+            opts.no_color,
+        );
+    }
+    for env in added {
+        print_success(
+            &format!(
+                "Added {} ({}) at {}",
+                env.name,
+                env.env_type,
+                env.path.display()
+            ),
+            opts.no_color,
+        );
+    }
+    Ok(())
+}
+
+/// Whether `path` is an entry marking its folder as an environment, the way
+/// scans tell (conda prefixes outside pixi are found through conda itself)
+fn is_marker(path: &Path) -> bool {
+    match path.file_name() {
+        Some(name) if name == "pyvenv.cfg" => true,
+        Some(name) if name == "conda-meta" => is_pixi_env(path),
+        _ => false,
+    }
+}
+
+fn is_symlink(path: &Path) -> bool {
+    fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_symlink())
+}
+
+fn is_environment_root(dir: &Path) -> bool {
+    dir.join("pyvenv.cfg").is_file() || dir.join("conda-meta").is_dir()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::{CACHE_VERSION, CacheFile, CacheHeader, load_cache, save_cache};
+    use std::env;
+
+    #[test]
+    fn apply_survives_paths_that_vanished() {
+        let dir = env::temp_dir().join(format!("venv-watch-test-{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).expect("failed to create the test directory");
+        let quiet = Options::default();
+        let cache_file = dir.join("python_venv_cache.json");
+        let venv = dir.join("project").join(".venv");
+        let cached: Environment = serde_json::from_value(serde_json::json!({
+            "name": "project",
+            "env_type": "venv",
+            "path": venv,
+        }))
+        .expect("valid environment");
+        let cache = CacheFile {
+            header: CacheHeader {
+                version: CACHE_VERSION,
+                tool_version: "test".to_string(),
+                created: 0,
+                scan_roots: vec![dir.clone()],
+                config_hash: String::new(),
+                scan_ms: 0,
+            },
+            environments: vec![cached],
+        };
+        save_cache(&cache_file, &cache, &quiet).expect("save");
+
+        let (tx, _rx) = mpsc::channel();
+        let mut watched = Watched {
+            watcher: notify::recommended_watcher(tx).expect("watcher"),
+            roots: vec![dir.clone()],
+            dirs: HashMap::new(),
+            failed: 0,
+            first_error: None,
+        };
+        watched.add(&dir, None);
+        let excludes = Excludes::new(&dir, &[], &dir.join("config.toml"), false).expect("excludes");
+
+        // Reported as created, then deleted before the events were handled
+        fs::create_dir_all(&venv).expect("create");
+        fs::remove_dir_all(dir.join("project")).expect("remove");
+        let paths = BTreeSet::from([dir.join("project"), venv.join("pyvenv.cfg")]);
+        apply(paths, &mut watched, &excludes, &cache_file, &quiet)
+            .expect("vanished paths are not an error");
+
+        let environments = load_cache(&cache_file, &quiet).expect("cache");
+        let _ = fs::remove_dir_all(&dir);
+        assert!(environments.is_empty(), "kept {:?}", environments);
+    }
+}